@@ -0,0 +1,302 @@
+use crate::{Buttons, Controller, ControllerType, Joystick, Triggers};
+
+/// The largest magnitude of a normalized stick axis
+pub const AXIS_MAX: i16 = i16::MAX;
+
+/// The default `Analog` deadzone, about 20% of full deflection
+pub const DEFAULT_DEADZONE: Deadzone = Deadzone::Radial(6_500);
+/// The default deflection, in normalized units, where a stick emulates a dpad press. Half of full deflection
+pub const DEFAULT_DPAD_THRESHOLD: i16 = AXIS_MAX / 2;
+
+/// How stick positions near the center are ignored. Sizes are in normalized units, from 0 to `AXIS_MAX`.
+///
+/// Positions outside of the deadzone are rescaled, so output still starts at 0 and reaches full deflection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deadzone {
+    /// No deadzone
+    None,
+    /// Each axis is zeroed independently. Makes it easy to hold a stick on exactly one axis
+    Axial(i16),
+    /// The stick is zeroed when its distance from the center is within the deadzone. Preserves the stick's direction
+    Radial(i16),
+}
+
+/// A processed analog stick position.
+///
+/// Each axis is normalized from `-AXIS_MAX` to `AXIS_MAX`, and keeps the raw orientation: positive `x` is right, and
+/// positive `y` is down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stick {
+    pub x: i16,
+    pub y: i16,
+}
+
+impl Stick {
+    pub const CENTERED: Stick = Stick { x: 0, y: 0 };
+
+    pub fn is_centered(&self) -> bool {
+        self.x == 0 && self.y == 0
+    }
+
+    ///
+    /// `x` from -1.0 to 1.0
+    ///
+    pub fn x_f32(&self) -> f32 {
+        self.x as f32 / AXIS_MAX as f32
+    }
+
+    ///
+    /// `y` from -1.0 to 1.0
+    ///
+    pub fn y_f32(&self) -> f32 {
+        self.y as f32 / AXIS_MAX as f32
+    }
+
+    ///
+    /// The dpad directions the stick is pushed past `threshold` in, on each axis
+    ///
+    pub fn dpad(&self, threshold: i16) -> Buttons {
+        let mut buttons = Buttons::empty();
+
+        buttons.set(Buttons::DPAD_UP, self.y < -threshold);
+        buttons.set(Buttons::DPAD_DOWN, self.y > threshold);
+        buttons.set(Buttons::DPAD_LEFT, self.x < -threshold);
+        buttons.set(Buttons::DPAD_RIGHT, self.x > threshold);
+
+        buttons
+    }
+}
+
+/// Processed analog trigger levels, from 0 to `u16::MAX`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TriggerLevels {
+    pub left: u16,
+    pub right: u16,
+}
+
+impl TriggerLevels {
+    ///
+    /// `left` from 0.0 to 1.0
+    ///
+    pub fn left_f32(&self) -> f32 {
+        self.left as f32 / u16::MAX as f32
+    }
+
+    ///
+    /// `right` from 0.0 to 1.0
+    ///
+    pub fn right_f32(&self) -> f32 {
+        self.right as f32 / u16::MAX as f32
+    }
+}
+
+/// Converts the raw sticks and triggers of one controller into normalized values, with deadzones and calibration.
+///
+/// Can also emulate the dpad with the left stick, so games written for the Pocket's buttons work unchanged with a
+/// docked analog controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Analog {
+    deadzone: Deadzone,
+    trigger_deadzone: u8,
+    center: Joystick,
+    dpad_threshold: Option<i16>,
+}
+
+impl Analog {
+    pub const fn new() -> Self {
+        Analog {
+            deadzone: DEFAULT_DEADZONE,
+            trigger_deadzone: 0,
+            center: Joystick::CENTERED,
+            dpad_threshold: None,
+        }
+    }
+
+    pub const fn with_deadzone(mut self, deadzone: Deadzone) -> Self {
+        self.deadzone = deadzone;
+        self
+    }
+
+    ///
+    /// Ignores raw trigger values up to `deadzone`
+    ///
+    pub const fn with_trigger_deadzone(mut self, deadzone: u8) -> Self {
+        self.trigger_deadzone = deadzone;
+        self
+    }
+
+    ///
+    /// Enables dpad emulation, pressing a direction when the left stick is past `threshold` on that axis
+    ///
+    pub const fn with_dpad_emulation(mut self, threshold: i16) -> Self {
+        self.dpad_threshold = Some(threshold);
+        self
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+    }
+
+    pub fn set_trigger_deadzone(&mut self, deadzone: u8) {
+        self.trigger_deadzone = deadzone;
+    }
+
+    ///
+    /// Sets the dpad emulation threshold, or disables emulation with `None`
+    ///
+    pub fn set_dpad_emulation(&mut self, threshold: Option<i16>) {
+        self.dpad_threshold = threshold;
+    }
+
+    ///
+    /// Records `joystick` as the rest position of both sticks. Call while the sticks are untouched
+    ///
+    pub fn calibrate(&mut self, joystick: Joystick) {
+        self.center = joystick;
+    }
+
+    ///
+    /// The raw rest position of both sticks
+    ///
+    pub fn center(&self) -> Joystick {
+        self.center
+    }
+
+    pub fn left_stick(&self, joystick: Joystick) -> Stick {
+        self.stick(
+            joystick.lstick_x,
+            joystick.lstick_y,
+            self.center.lstick_x,
+            self.center.lstick_y,
+        )
+    }
+
+    pub fn right_stick(&self, joystick: Joystick) -> Stick {
+        self.stick(
+            joystick.rstick_x,
+            joystick.rstick_y,
+            self.center.rstick_x,
+            self.center.rstick_y,
+        )
+    }
+
+    pub fn triggers(&self, triggers: Triggers) -> TriggerLevels {
+        TriggerLevels {
+            left: self.trigger(triggers.left),
+            right: self.trigger(triggers.right),
+        }
+    }
+
+    ///
+    /// The held buttons of `controller`, including emulated dpad presses if enabled. Only analog controllers emulate
+    /// the dpad, as the sticks of other controllers don't report meaningful values
+    ///
+    pub fn buttons(&self, controller: &Controller) -> Buttons {
+        match self.dpad_threshold {
+            Some(threshold)
+                if controller.controller_type == ControllerType::DockedAnalogGamepad =>
+            {
+                controller.buttons | self.left_stick(controller.joystick).dpad(threshold)
+            }
+            _ => controller.buttons,
+        }
+    }
+
+    fn stick(&self, x: u8, y: u8, center_x: u8, center_y: u8) -> Stick {
+        let x = normalize(x, center_x);
+        let y = normalize(y, center_y);
+
+        let (x, y) = match self.deadzone {
+            Deadzone::None => (x, y),
+            Deadzone::Axial(size) => (rescale(x, size), rescale(y, size)),
+            Deadzone::Radial(size) => {
+                let magnitude = isqrt((x * x) as u32 + (y * y) as u32) as i32;
+                let scaled = rescale(magnitude.min(AXIS_MAX as i32), size);
+
+                if scaled == 0 {
+                    (0, 0)
+                } else {
+                    (x * scaled / magnitude, y * scaled / magnitude)
+                }
+            }
+        };
+
+        Stick {
+            x: clamp_axis(x),
+            y: clamp_axis(y),
+        }
+    }
+
+    fn trigger(&self, value: u8) -> u16 {
+        let deadzone = self.trigger_deadzone as u32;
+        let value = value as u32;
+
+        if value <= deadzone {
+            0
+        } else {
+            ((value - deadzone) * u16::MAX as u32 / (255 - deadzone)) as u16
+        }
+    }
+}
+
+impl Default for Analog {
+    fn default() -> Self {
+        Analog::new()
+    }
+}
+
+// Maps a raw axis to +-`AXIS_MAX`. Each side of the center is scaled separately, so both reach full deflection
+fn normalize(raw: u8, center: u8) -> i32 {
+    let offset = raw as i32 - center as i32;
+    let range = if offset < 0 {
+        center as i32
+    } else {
+        255 - center as i32
+    };
+
+    if range == 0 {
+        0
+    } else {
+        offset * AXIS_MAX as i32 / range
+    }
+}
+
+// Zeroes `value` within `deadzone` of 0, and scales the remainder back up to full range
+fn rescale(value: i32, deadzone: i16) -> i32 {
+    let max = AXIS_MAX as i32;
+    let deadzone = (deadzone as i32).clamp(0, max - 1);
+    let magnitude = value.abs().min(max);
+
+    if magnitude <= deadzone {
+        0
+    } else {
+        value.signum() * (magnitude - deadzone) * max / (max - deadzone)
+    }
+}
+
+fn clamp_axis(value: i32) -> i16 {
+    value.clamp(-(AXIS_MAX as i32), AXIS_MAX as i32) as i16
+}
+
+fn isqrt(value: u32) -> u32 {
+    let mut result = 0;
+    let mut remainder = value;
+    let mut bit = 1 << 30;
+
+    while bit > value {
+        bit >>= 2;
+    }
+
+    while bit != 0 {
+        if remainder >= result + bit {
+            remainder -= result + bit;
+            result = (result >> 1) + bit;
+        } else {
+            result >>= 1;
+        }
+
+        bit >>= 2;
+    }
+
+    result
+}
@@ -0,0 +1,197 @@
+use litex_pac as pac;
+
+/// The number of entries in the hardware audio FIFO. The FIFO plays at 48kHz
+pub const AUDIO_FIFO_CAPACITY: usize = 4096;
+
+// `buffer_fill` is 12 bits wide and wraps to 0 when the FIFO is completely full, so leave one entry free
+const AUDIO_FIFO_LIMIT: usize = AUDIO_FIFO_CAPACITY - 1;
+
+// Samples rendered onto the stack at a time by `Audio::fill_to`
+const RENDER_CHUNK: usize = 128;
+
+/// A single 48kHz sample for both channels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StereoSample {
+    pub left: i16,
+    pub right: i16,
+}
+
+impl StereoSample {
+    pub const SILENCE: StereoSample = StereoSample::mono(0);
+
+    pub const fn new(left: i16, right: i16) -> Self {
+        StereoSample { left, right }
+    }
+
+    ///
+    /// A sample with the same value on both channels
+    ///
+    pub const fn mono(value: i16) -> Self {
+        StereoSample {
+            left: value,
+            right: value,
+        }
+    }
+
+    ///
+    /// Packs the sample in the layout `APF_AUDIO.out` expects, left channel in the high half
+    ///
+    pub const fn to_bits(self) -> u32 {
+        ((self.left as u16 as u32) << 16) | (self.right as u16 as u32)
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        StereoSample {
+            left: (bits >> 16) as u16 as i16,
+            right: bits as u16 as i16,
+        }
+    }
+}
+
+/// Owned access to the `APF_AUDIO` block.
+///
+/// Tracks underruns, where the FIFO is found empty during playback after samples were queued. Underruns can only be
+/// detected when the fill level is sampled, by `push_samples` or `poll_fill`, so poll at least once per frame.
+pub struct Audio {
+    registers: pac::APF_AUDIO,
+    playing: bool,
+    // Whether samples were queued as of the last time the fill level was sampled
+    queued: bool,
+    underruns: u32,
+}
+
+impl Audio {
+    pub fn new(registers: pac::APF_AUDIO) -> Self {
+        Audio {
+            registers,
+            playing: false,
+            queued: false,
+            underruns: 0,
+        }
+    }
+
+    pub fn free(self) -> pac::APF_AUDIO {
+        self.registers
+    }
+
+    ///
+    /// The number of samples currently queued in the 4096 entry audio FIFO
+    ///
+    pub fn buffer_fill(&self) -> u16 {
+        self.registers.buffer_fill.read().buffer_fill().bits()
+    }
+
+    ///
+    /// Samples the FIFO fill level, counting an underrun if it has drained since it was last sampled
+    ///
+    pub fn poll_fill(&mut self) -> u16 {
+        let fill = self.buffer_fill();
+
+        if self.playing && self.queued && fill == 0 {
+            self.underruns = self.underruns.wrapping_add(1);
+        }
+
+        self.queued = fill > 0;
+
+        fill
+    }
+
+    ///
+    /// The number of samples that can be pushed without overfilling the FIFO
+    ///
+    pub fn free_space(&mut self) -> usize {
+        AUDIO_FIFO_LIMIT.saturating_sub(self.poll_fill() as usize)
+    }
+
+    ///
+    /// Queues as many of `samples` as fit in the FIFO, returning the number queued
+    ///
+    pub fn push_samples(&mut self, samples: &[StereoSample]) -> usize {
+        let count = samples.len().min(self.free_space());
+
+        for sample in &samples[..count] {
+            self.write_raw(sample.to_bits());
+        }
+
+        self.queued |= count > 0;
+
+        count
+    }
+
+    ///
+    /// Tops the FIFO up to `target` samples, at most one less than its capacity, with audio from `render`. `render`
+    /// fills the start of a chunk of at most 128 samples, returning the number written. Stops early if it writes none.
+    /// Returns the number of samples pushed
+    ///
+    pub fn fill_to(
+        &mut self,
+        target: usize,
+        mut render: impl FnMut(&mut [StereoSample]) -> usize,
+    ) -> usize {
+        let mut remaining = target
+            .min(AUDIO_FIFO_LIMIT)
+            .saturating_sub(self.poll_fill() as usize);
+        let mut pushed = 0;
+        let mut chunk = [StereoSample::SILENCE; RENDER_CHUNK];
+
+        while remaining > 0 {
+            let count = render(&mut chunk[..remaining.min(RENDER_CHUNK)]);
+
+            if count == 0 {
+                break;
+            }
+
+            let accepted = self.push_samples(&chunk[..count]);
+            pushed += accepted;
+
+            if accepted < count {
+                // The FIFO is full. The rest of this chunk is lost
+                break;
+            }
+
+            remaining -= count;
+        }
+
+        pushed
+    }
+
+    ///
+    /// Pushes a single 48kHz sample into the audio FIFO. `value` contains two packed `i16`s, one per channel
+    ///
+    /// **NOTE:** This does not check for space in the FIFO. Prefer `push_samples`
+    ///
+    pub fn write_raw(&mut self, value: u32) {
+        unsafe { self.registers.out.write(|w| w.bits(value)) };
+    }
+
+    ///
+    /// Immediately discards every queued sample
+    ///
+    pub fn flush(&mut self) {
+        unsafe { self.registers.buffer_flush.write(|w| w.bits(1)) };
+
+        // An intentionally emptied FIFO isn't an underrun
+        self.queued = false;
+    }
+
+    pub fn set_playback(&mut self, enabled: bool) {
+        unsafe { self.registers.playback_en.write(|w| w.bits(enabled as u32)) };
+
+        self.playing = enabled;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    ///
+    /// The number of underruns detected since creation or the last `reset_underruns`
+    ///
+    pub fn underruns(&self) -> u32 {
+        self.underruns
+    }
+
+    pub fn reset_underruns(&mut self) {
+        self.underruns = 0;
+    }
+}
@@ -0,0 +1,131 @@
+use litex_pac as pac;
+
+use crate::{MAIN_RAM_BASE, MAIN_RAM_LENGTH};
+
+/// A bridge command, started with `BridgeBackend::start_request`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BridgeRequest {
+    /// Host command 0x0180. Copy from the slot's file into RAM
+    Read,
+    /// Host command 0x0184. Copy from RAM into the slot's file
+    Write,
+    /// Host command 0x0190. Write the slot's null terminated path into RAM
+    GetFile,
+    /// Host command 0x0192. Switch the slot to the file described by the struct in RAM
+    OpenFile,
+}
+
+/// The register level operations `File` is built on.
+///
+/// `pac::APF_BRIDGE` implements this for the real bridge. With the `std` feature enabled, `MockBridge` implements it
+/// over a host directory, so file code can be tested off device.
+///
+/// Like the hardware registers, every operation takes `&self`.
+pub trait BridgeBackend {
+    ///
+    /// True if the bridge can transfer `length` bytes to or from RAM at `address`
+    ///
+    fn can_access(&self, address: usize, length: usize) -> bool;
+
+    ///
+    /// Starts `request` on slot `bridge_slot_id`, transferring `length` bytes between `data_offset` of the file and RAM
+    /// at `storage_address`
+    ///
+    fn start_request(
+        &self,
+        request: BridgeRequest,
+        data_offset: u32,
+        length: u32,
+        storage_address: usize,
+        bridge_slot_id: u32,
+    );
+
+    ///
+    /// Returns true once the last request has finished. The completion flag is cleared on read
+    ///
+    fn check_op_complete(&self) -> bool;
+
+    ///
+    /// The raw result code of the most recently completed command. 0 is success
+    ///
+    fn command_result_code(&self) -> u8;
+
+    ///
+    /// The RAM address the bridge is currently operating on
+    ///
+    fn current_address(&self) -> u32;
+
+    fn file_size(&self, bridge_slot_id: u32) -> u32;
+
+    fn set_file_size(&self, bridge_slot_id: u32, size: u32);
+}
+
+impl BridgeBackend for pac::APF_BRIDGE {
+    fn can_access(&self, address: usize, length: usize) -> bool {
+        let start = address as u64;
+        let end = start + length as u64;
+
+        start >= MAIN_RAM_BASE as u64 && end <= MAIN_RAM_BASE as u64 + MAIN_RAM_LENGTH as u64
+    }
+
+    fn start_request(
+        &self,
+        request: BridgeRequest,
+        data_offset: u32,
+        length: u32,
+        storage_address: usize,
+        bridge_slot_id: u32,
+    ) {
+        unsafe {
+            self.data_offset.write(|w| w.bits(data_offset));
+
+            self.transfer_length.write(|w| w.bits(length));
+            self.ram_data_address
+                .write(|w| w.bits(storage_address as u32));
+            self.slot_id.write(|w| w.bits(bridge_slot_id));
+
+            match request {
+                BridgeRequest::Read => self.request_read.write(|w| w.bits(1)),
+                BridgeRequest::Write => self.request_write.write(|w| w.bits(1)),
+                BridgeRequest::GetFile => self.request_getfile.write(|w| w.bits(1)),
+                BridgeRequest::OpenFile => self.request_openfile.write(|w| w.bits(1)),
+            }
+        };
+    }
+
+    fn check_op_complete(&self) -> bool {
+        self.status.read().bits() == 1
+    }
+
+    fn command_result_code(&self) -> u8 {
+        self.command_result_code.read().command_result_code().bits()
+    }
+
+    fn current_address(&self) -> u32 {
+        self.current_address.read().bits()
+    }
+
+    fn file_size(&self, bridge_slot_id: u32) -> u32 {
+        unsafe {
+            self.slot_id.write(|w| w.bits(bridge_slot_id));
+        }
+
+        // Ensure slot change and size read has occured, as it takes several cycles
+        self.slot_id.read().bits();
+
+        self.file_size.read().bits()
+    }
+
+    fn set_file_size(&self, bridge_slot_id: u32, size: u32) {
+        unsafe {
+            self.slot_id.write(|w| w.bits(bridge_slot_id));
+        }
+
+        // Ensure slot change has occured, as it takes several cycles
+        self.slot_id.read().bits();
+
+        unsafe {
+            self.file_size.write(|w| w.bits(size));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use litex_pac as pac;
+
+/// Owned access to the `CTRL` block.
+pub struct Ctrl {
+    registers: pac::CTRL,
+}
+
+impl Ctrl {
+    pub fn new(registers: pac::CTRL) -> Self {
+        Ctrl { registers }
+    }
+
+    pub fn free(self) -> pac::CTRL {
+        self.registers
+    }
+
+    ///
+    /// Resets the entire SoC
+    ///
+    pub fn reset_soc(&mut self) {
+        self.registers.reset.write(|w| w.soc_rst().set_bit());
+    }
+
+    ///
+    /// Resets the CPU, leaving the rest of the SoC untouched
+    ///
+    pub fn reset_cpu(&mut self) {
+        self.registers.reset.write(|w| w.cpu_rst().set_bit());
+    }
+}
@@ -0,0 +1,278 @@
+use alloc::vec::Vec;
+use core::{fmt, ops::Range};
+
+use crate::{Rect, FRAMEBUFFER_HEIGHT, FRAMEBUFFER_PIXELS, FRAMEBUFFER_WIDTH};
+
+/// The most separate dirty rectangles tracked in a frame before they're merged into one
+pub const MAX_DIRTY_RECTS: usize = 16;
+
+/// Totals of how much of the screen was redrawn, for measuring the gain from partial updates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RedrawStats {
+    /// Frames rendered
+    pub frames: u32,
+    /// Pixels within the region redrawn each frame
+    pub pixels_redrawn: u64,
+    /// Rows copied from the latest frame to bring an older buffer up to date
+    pub rows_copied: u64,
+}
+
+impl RedrawStats {
+    ///
+    /// The average fraction of the screen redrawn per frame, from 0 to 1
+    ///
+    pub fn redrawn_fraction(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+
+        self.pixels_redrawn as f32 / (self.frames as u64 * FRAMEBUFFER_PIXELS as u64) as f32
+    }
+}
+
+impl fmt::Display for RedrawStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, {:.1}% redrawn, {} rows copied",
+            self.frames,
+            self.redrawn_fraction() * 100.0,
+            self.rows_copied
+        )
+    }
+}
+
+/// The areas of the screen that need redrawing. Overlapping rectangles are merged, and past `MAX_DIRTY_RECTS` they're
+/// all merged into one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirtyRegions {
+    bounds: Rect,
+    rects: Vec<Rect>,
+}
+
+impl DirtyRegions {
+    ///
+    /// Tracks changes within `bounds`, usually the whole screen
+    ///
+    pub fn new(bounds: Rect) -> Self {
+        DirtyRegions {
+            bounds,
+            rects: Vec::new(),
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.rects == [self.bounds]
+    }
+
+    pub fn add(&mut self, rect: Rect) {
+        let mut rect = rect.intersection(&self.bounds);
+
+        if rect.is_empty() {
+            return;
+        }
+
+        // Absorb everything the new rectangle overlaps, repeating as it grows
+        let mut index = 0;
+
+        while index < self.rects.len() {
+            if self.rects[index].intersects(&rect) {
+                rect = rect.union(&self.rects.swap_remove(index));
+                index = 0;
+            } else {
+                index += 1;
+            }
+        }
+
+        if self.rects.len() >= MAX_DIRTY_RECTS {
+            rect = self
+                .rects
+                .drain(..)
+                .fold(rect, |merged, other| merged.union(&other));
+        }
+
+        self.rects.push(rect);
+    }
+
+    ///
+    /// Marks everything as dirty
+    ///
+    pub fn add_all(&mut self) {
+        self.rects.clear();
+
+        if !self.bounds.is_empty() {
+            self.rects.push(self.bounds);
+        }
+    }
+
+    pub fn extend(&mut self, other: &DirtyRegions) {
+        for &rect in &other.rects {
+            self.add(rect);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+}
+
+/// Tracks the regions redrawn by each of the last `N` renders, and which of them each of `N` framebuffers holds, so a
+/// buffer can be brought up to date by redrawing or copying only what changed since it was last drawn.
+///
+/// A renderer that only redraws what changed since its last frame assumes its buffer holds that frame. With more than
+/// one buffer it doesn't, so before drawing, copy `stale_rows` of the back buffer from the `latest` buffer, then
+/// `record` what was drawn. A renderer that redraws from its own state instead records each render, then redraws
+/// `damage_since` the age of the buffer it draws into.
+#[derive(Clone, Debug)]
+pub struct BufferDamage<const N: usize> {
+    // The render each buffer last held
+    rendered: [Option<u32>; N],
+    // The damage of the last `N` renders, oldest first
+    history: Vec<DirtyRegions>,
+    renders: u32,
+    latest: Option<usize>,
+    stats: RedrawStats,
+}
+
+impl<const N: usize> BufferDamage<N> {
+    pub fn new() -> Self {
+        BufferDamage {
+            rendered: [None; N],
+            history: Vec::with_capacity(N),
+            renders: 0,
+            latest: None,
+            stats: RedrawStats::default(),
+        }
+    }
+
+    ///
+    /// The buffer holding the most recently rendered frame, if any
+    ///
+    pub fn latest(&self) -> Option<usize> {
+        self.latest
+    }
+
+    ///
+    /// The regions that changed since the render `age` renders ago, including the latest. Everything if `age` is 0,
+    /// meaning unknown, or reaches before the history or the first render
+    ///
+    pub fn damage_since(&self, age: usize) -> DirtyRegions {
+        let mut damage = DirtyRegions::new(self.bounds());
+
+        if age == 0 || age > self.history.len() || age >= self.renders as usize {
+            damage.add_all();
+        } else {
+            for regions in &self.history[self.history.len() - age..] {
+                damage.extend(regions);
+            }
+        }
+
+        damage
+    }
+
+    ///
+    /// The regions of `buffer` that differ from the latest frame. Everything if `buffer` has never been drawn, or is
+    /// older than the history
+    ///
+    pub fn damage(&self, buffer: usize) -> DirtyRegions {
+        match self.rendered[buffer] {
+            Some(render) if render == self.renders => DirtyRegions::new(self.bounds()),
+            Some(render) => self.damage_since((self.renders - render) as usize),
+            None => self.damage_since(0),
+        }
+    }
+
+    ///
+    /// The rows of `buffer` that differ from the latest frame. Every row if `buffer` has never been drawn, or is older
+    /// than the history
+    ///
+    pub fn stale_rows(&self, buffer: usize) -> Range<usize> {
+        let damage = self
+            .damage(buffer)
+            .rects()
+            .iter()
+            .fold(Rect::default(), |damage, rect| damage.union(rect))
+            .intersection(&Self::screen());
+
+        if damage.is_empty() {
+            0..0
+        } else {
+            damage.y as usize..damage.bottom() as usize
+        }
+    }
+
+    ///
+    /// Records that `damage` was redrawn into `buffer`, which now holds the latest frame
+    ///
+    pub fn record(&mut self, buffer: usize, damage: Rect) {
+        let mut regions = DirtyRegions::new(Self::screen());
+
+        regions.add(damage);
+        self.record_render(regions);
+        self.rendered[buffer] = Some(self.renders);
+        self.latest = Some(buffer);
+    }
+
+    ///
+    /// Records a render that changed `damage`, without tracking the buffer it was drawn into
+    ///
+    pub fn record_render(&mut self, damage: DirtyRegions) {
+        if self.history.len() == N {
+            self.history.remove(0);
+        }
+
+        self.stats.frames += 1;
+        self.stats.pixels_redrawn += damage
+            .rects()
+            .iter()
+            .map(|rect| rect.width as u64 * rect.height as u64)
+            .sum::<u64>();
+
+        self.history.push(damage);
+        self.renders += 1;
+    }
+
+    ///
+    /// Adds `rows` to the rows copied in `stats`
+    ///
+    pub fn record_copy(&mut self, rows: Range<usize>) {
+        self.stats.rows_copied += rows.len() as u64;
+    }
+
+    pub fn stats(&self) -> RedrawStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = RedrawStats::default();
+    }
+
+    // The bounds of the latest render
+    fn bounds(&self) -> Rect {
+        self.history
+            .last()
+            .map_or_else(Self::screen, DirtyRegions::bounds)
+    }
+
+    fn screen() -> Rect {
+        Rect::new(0, 0, FRAMEBUFFER_WIDTH as u32, FRAMEBUFFER_HEIGHT as u32)
+    }
+}
+
+impl<const N: usize> Default for BufferDamage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,74 @@
+use core::fmt;
+
+use litex_pac as pac;
+
+/// The 64 bit Cyclone V chip ID, unique to each Pocket.
+///
+/// Displays as 16 lowercase hex digits, with leading zeros, so it's stable for use as an identifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChipId(pub u64);
+
+impl ChipId {
+    ///
+    /// The low 32 bits, from `APF_ID.id0`
+    ///
+    pub const fn low(&self) -> u32 {
+        self.0 as u32
+    }
+
+    ///
+    /// The high 32 bits, from `APF_ID.id1`
+    ///
+    pub const fn high(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+}
+
+impl From<ChipId> for u64 {
+    fn from(id: ChipId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for ChipId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl fmt::LowerHex for ChipId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::UpperHex for ChipId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+/// Owned access to the `APF_ID` block.
+pub struct Device {
+    registers: pac::APF_ID,
+}
+
+impl Device {
+    pub fn new(registers: pac::APF_ID) -> Self {
+        Device { registers }
+    }
+
+    pub fn free(self) -> pac::APF_ID {
+        self.registers
+    }
+
+    ///
+    /// The 64 bit Cyclone V chip ID
+    ///
+    pub fn chip_id(&self) -> ChipId {
+        let low_bits = self.registers.id0.read().bits();
+        let high_bits = self.registers.id1.read().bits();
+
+        ChipId(((high_bits as u64) << 32) | (low_bits as u64))
+    }
+}
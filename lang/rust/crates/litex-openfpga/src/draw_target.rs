@@ -0,0 +1,93 @@
+use core::convert::Infallible;
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Size},
+    pixelcolor::{raw::RawU16, IntoStorage},
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{Canvas, Rect, Rgb565};
+
+impl From<embedded_graphics_core::pixelcolor::Rgb565> for Rgb565 {
+    fn from(color: embedded_graphics_core::pixelcolor::Rgb565) -> Self {
+        Rgb565(color.into_storage())
+    }
+}
+
+impl From<Rgb565> for embedded_graphics_core::pixelcolor::Rgb565 {
+    fn from(color: Rgb565) -> Self {
+        RawU16::new(color.0).into()
+    }
+}
+
+fn to_rect(area: &Rectangle) -> Rect {
+    Rect::new(
+        area.top_left.x,
+        area.top_left.y,
+        area.size.width,
+        area.size.height,
+    )
+}
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.width() as u32, self.height() as u32)
+    }
+}
+
+/// Draws `embedded-graphics` primitives, text, and images, clipped to the canvas and in the canvas' `DrawMode`
+impl DrawTarget for Canvas<'_> {
+    type Color = embedded_graphics_core::pixelcolor::Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.set_pixel(point.x, point.y, color.into());
+        }
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let area = to_rect(area);
+        let width = area.width as usize;
+
+        if width == 0 {
+            return Ok(());
+        }
+
+        // Colors are in row major order over the whole area, including any clipped part
+        for (index, color) in colors
+            .into_iter()
+            .take(width * area.height as usize)
+            .enumerate()
+        {
+            let x = area.x + (index % width) as i32;
+            let y = area.y + (index / width) as i32;
+
+            self.set_pixel(x, y, color.into());
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_rect(to_rect(area), color.into());
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        Canvas::clear(self, color.into());
+
+        Ok(())
+    }
+}
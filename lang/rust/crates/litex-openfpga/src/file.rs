@@ -1,68 +1,526 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::{Context, Poll};
+use heapless::String;
 use litex_pac as pac;
 
-pub struct File;
+use crate::{BridgeBackend, BridgeRequest};
 
-impl File {
+/// Start of `main_ram`, from `lang/linker/memory.x`. The bridge can only transfer to and from this region
+pub const MAIN_RAM_BASE: u32 = 0x4000_0000;
+/// Length of `main_ram`, from `lang/linker/memory.x`
+pub const MAIN_RAM_LENGTH: u32 = 0x0400_0000;
+
+/// Owned access to the `APF_BRIDGE` block, used for file IO.
+///
+/// Generic over the `BridgeBackend`, so the same file code can run against `MockBridge` on a host.
+pub struct File<B: BridgeBackend = pac::APF_BRIDGE> {
+    backend: B,
+}
+
+/// A failed bridge operation, decoded from `command_result_code`.
+///
+/// See https://www.analogue.co/developer/docs/host-target-commands for the codes each command can return.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BridgeError {
+    /// Result code 1. The slot ID is not defined in `data.json`
+    SlotNotFound,
+    /// Result code 2. The requested offset or length falls outside of the file
+    OutOfRange,
+    /// Result code 3. The host failed to access the file on disk
+    FileError,
+    /// Any other nonzero result code
+    Unknown(u8),
+}
+
+impl BridgeError {
+    ///
+    /// Decodes a `command_result_code`, where 0 is success
+    ///
+    pub fn from_result_code(code: u8) -> Result<(), BridgeError> {
+        match code {
+            0 => Ok(()),
+            1 => Err(BridgeError::SlotNotFound),
+            2 => Err(BridgeError::OutOfRange),
+            3 => Err(BridgeError::FileError),
+            code => Err(BridgeError::Unknown(code)),
+        }
+    }
+}
+
+/// The maximum length of a slot's file path, including the null terminator
+pub const MAX_PATH_LENGTH: usize = 256;
+
+// Size of the aligned buffer unaligned writes are staged through
+const WRITE_STAGING_WORDS: usize = 64;
+
+// Path, flags, and size
+const OPEN_FILE_STRUCT_LENGTH: usize = MAX_PATH_LENGTH + 8;
+
+/// Options for `File::open_path_with`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpenFlags {
+    /// Create the file if it doesn't exist
+    pub create: bool,
+    /// Resize the file to `size` bytes
+    pub resize: bool,
+    pub size: u32,
+}
+
+impl OpenFlags {
+    fn bits(&self) -> u32 {
+        (self.create as u32) | ((self.resize as u32) << 1)
+    }
+}
+
+/// A failed `File::get_path` or `File::open_path`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// The path is too long, contains a null byte, or isn't valid UTF-8
+    InvalidPath,
+    /// The bridge reported an error for the command
+    Bridge(BridgeError),
+}
+
+impl From<BridgeError> for PathError {
+    fn from(error: BridgeError) -> Self {
+        PathError::Bridge(error)
+    }
+}
+
+/// A failed `File::read_into` or `SlotReader` operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadError {
+    /// The requested offset is past the end of the file in the slot
+    OffsetPastEnd,
+    /// The destination buffer does not lie entirely within `main_ram`
+    BufferOutsideRam,
+    /// A seek to a position before the start of the file, or beyond what the bridge can address
+    InvalidSeek,
+    /// The bridge reported an error during the transfer
+    Bridge(BridgeError),
+}
+
+impl From<BridgeError> for ReadError {
+    fn from(error: BridgeError) -> Self {
+        ReadError::Bridge(error)
+    }
+}
+
+/// An in flight bridge operation, returned when a request is started.
+///
+/// The transfer borrows the `File` it was started on, so no other bridge operation can be requested until it is
+/// dropped. The bridge `status` register clears when read, so once complete, the result is cached in the handle.
+///
+/// The operation starts as soon as it is requested. Dropping the transfer doesn't cancel it, and the bridge keeps
+/// accessing RAM until it finishes.
+#[must_use = "the bridge operation has already started, and its result is only reported by polling the transfer"]
+pub struct BridgeTransfer<'a, B: BridgeBackend = pac::APF_BRIDGE> {
+    file: &'a mut File<B>,
+    address: u32,
+    length: u32,
+    result: Option<Result<usize, BridgeError>>,
+}
+
+impl<'a, B: BridgeBackend> BridgeTransfer<'a, B> {
+    ///
+    /// Checks whether the transfer has finished, returning the number of bytes transferred once it has
+    ///
+    pub fn poll(&mut self) -> Poll<Result<usize, BridgeError>> {
+        if self.result.is_none() && self.file.check_op_complete() {
+            let result = BridgeError::from_result_code(self.file.command_result_code())
+                .map(|_| self.length as usize);
+
+            self.result = Some(result);
+        }
+
+        match self.result {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+
+    ///
+    /// Spins until the transfer finishes
+    ///
+    pub fn wait(mut self) -> Result<usize, BridgeError> {
+        loop {
+            if let Poll::Ready(result) = self.poll() {
+                return result;
+            }
+        }
+    }
+
+    ///
+    /// The total number of bytes requested by this transfer
+    ///
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    ///
+    /// An estimate of the number of bytes transferred so far, from how far `current_address` has moved past the
+    /// start of the transfer in RAM
+    ///
+    pub fn progress(&self) -> u32 {
+        match self.result {
+            Some(_) => self.length,
+            None => self
+                .file
+                .current_address()
+                .saturating_sub(self.address)
+                .min(self.length),
+        }
+    }
+}
+
+impl<'a, B: BridgeBackend> Future for BridgeTransfer<'a, B> {
+    type Output = Result<usize, BridgeError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = BridgeTransfer::poll(self.get_mut());
+
+        if result.is_pending() {
+            // There are no bridge interrupts, so ask to be polled again immediately
+            cx.waker().wake_by_ref();
+        }
+
+        result
+    }
+}
+
+impl<B: BridgeBackend> File<B> {
+    pub fn new(backend: B) -> Self {
+        File { backend }
+    }
+
+    pub fn free(self) -> B {
+        self.backend
+    }
+
+    ///
+    /// Starts reading `read_length` bytes at `data_offset` of slot `bridge_slot_id` into RAM at `storage_address`
+    ///
     pub fn request_read(
+        &mut self,
         data_offset: u32,
         read_length: u32,
-        storage_address: u32,
+        storage_address: usize,
+        bridge_slot_id: u32,
+    ) -> BridgeTransfer<'_, B> {
+        self.start_request(
+            BridgeRequest::Read,
+            data_offset,
+            read_length,
+            storage_address,
+            bridge_slot_id,
+        )
+    }
+
+    ///
+    /// Starts writing `write_length` bytes from RAM at `storage_address` to `data_offset` of slot `bridge_slot_id`
+    ///
+    /// **NOTE:** Writes appear to be broken in the Pocket firmware at the moment. See `docs/control.md`
+    ///
+    pub fn request_write(
+        &mut self,
+        data_offset: u32,
+        write_length: u32,
+        storage_address: usize,
+        bridge_slot_id: u32,
+    ) -> BridgeTransfer<'_, B> {
+        self.start_request(
+            BridgeRequest::Write,
+            data_offset,
+            write_length,
+            storage_address,
+            bridge_slot_id,
+        )
+    }
+
+    ///
+    /// Retrieves the path of the file currently loaded into slot `bridge_slot_id`
+    ///
+    pub fn get_path(&mut self, bridge_slot_id: u32) -> Result<String<MAX_PATH_LENGTH>, PathError> {
+        // Response struct is the null terminated path
+        let mut response = [0u32; MAX_PATH_LENGTH / 4];
+
+        self.start_request(
+            BridgeRequest::GetFile,
+            0,
+            0,
+            response.as_mut_ptr() as usize,
+            bridge_slot_id,
+        )
+        .wait()?;
+
+        compiler_fence(Ordering::SeqCst);
+
+        let mut bytes = [0u8; MAX_PATH_LENGTH];
+
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(response.iter()) {
+            chunk.copy_from_slice(&unsafe { core::ptr::read_volatile(word) }.to_le_bytes());
+        }
+
+        let length = bytes
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(MAX_PATH_LENGTH);
+        let path = core::str::from_utf8(&bytes[..length]).map_err(|_| PathError::InvalidPath)?;
+
+        let mut string = String::new();
+        // `path` is at most `MAX_PATH_LENGTH` bytes, so this always fits
+        string.push_str(path).ok();
+
+        Ok(string)
+    }
+
+    ///
+    /// Switches slot `bridge_slot_id` to the file at `path`
+    ///
+    pub fn open_path(&mut self, bridge_slot_id: u32, path: &str) -> Result<(), PathError> {
+        self.open_path_with(bridge_slot_id, path, OpenFlags::default())
+    }
+
+    ///
+    /// Switches slot `bridge_slot_id` to the file at `path`, optionally creating or resizing it
+    ///
+    pub fn open_path_with(
+        &mut self,
         bridge_slot_id: u32,
-    ) {
-        unsafe {
-            let peripherals = pac::Peripherals::steal();
+        path: &str,
+        flags: OpenFlags,
+    ) -> Result<(), PathError> {
+        // Leave room for the null terminator
+        if path.len() >= MAX_PATH_LENGTH || path.as_bytes().contains(&0) {
+            return Err(PathError::InvalidPath);
+        }
+
+        // Parameter struct is the null terminated path, followed by the flags and file size. The bridge is big
+        // endian, so the words must be byte swapped
+        let mut bytes = [0u8; OPEN_FILE_STRUCT_LENGTH];
+
+        bytes[..path.len()].copy_from_slice(path.as_bytes());
+        bytes[MAX_PATH_LENGTH..MAX_PATH_LENGTH + 4].copy_from_slice(&flags.bits().to_be_bytes());
+        bytes[MAX_PATH_LENGTH + 4..].copy_from_slice(&flags.size.to_be_bytes());
+
+        let mut parameters = [0u32; OPEN_FILE_STRUCT_LENGTH / 4];
+
+        for (word, chunk) in parameters.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        compiler_fence(Ordering::SeqCst);
+
+        self.start_request(
+            BridgeRequest::OpenFile,
+            0,
+            0,
+            parameters.as_ptr() as usize,
+            bridge_slot_id,
+        )
+        .wait()?;
+
+        Ok(())
+    }
 
-            peripherals
-                .APF_BRIDGE
-                .data_offset
-                .write(|w| w.bits(data_offset));
+    fn start_request(
+        &mut self,
+        request: BridgeRequest,
+        data_offset: u32,
+        length: u32,
+        storage_address: usize,
+        bridge_slot_id: u32,
+    ) -> BridgeTransfer<'_, B> {
+        // Clear any stale completion flag so it can't be mistaken for this request finishing
+        self.check_op_complete();
 
-            peripherals
-                .APF_BRIDGE
-                .transfer_length
-                .write(|w| w.bits(read_length));
-            peripherals
-                .APF_BRIDGE
-                .ram_data_address
-                .write(|w| w.bits(storage_address));
-            peripherals
-                .APF_BRIDGE
-                .slot_id
-                .write(|w| w.bits(bridge_slot_id));
+        self.backend.start_request(
+            request,
+            data_offset,
+            length,
+            storage_address,
+            bridge_slot_id,
+        );
 
-            peripherals.APF_BRIDGE.request_read.write(|w| w.bits(1));
-        };
+        BridgeTransfer {
+            file: self,
+            // RAM addresses on the Pocket fit in 32 bits
+            address: storage_address as u32,
+            length,
+            result: None,
+        }
     }
 
-    pub fn size(bridge_slot_id: u32) -> u32 {
-        unsafe {
-            let peripherals = pac::Peripherals::steal();
+    ///
+    /// Reads from `offset` of slot `bridge_slot_id` into `buffer`, blocking until complete. Reads stop at the end of the
+    /// file, so this returns the number of bytes actually transferred
+    ///
+    pub fn read_into(
+        &mut self,
+        bridge_slot_id: u32,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<usize, ReadError> {
+        let size = self.size(bridge_slot_id);
+
+        if offset > size {
+            return Err(ReadError::OffsetPastEnd);
+        }
+
+        let length = buffer.len().min((size - offset) as usize);
+        let buffer = &mut buffer[..length];
+        let address = buffer.as_ptr() as usize;
+
+        if !self.backend.can_access(address, length) {
+            return Err(ReadError::BufferOutsideRam);
+        }
+
+        // The bridge writes whole words starting from a word aligned address. Transfer the unaligned head and
+        // tail through a single word, so nothing outside of `buffer` is overwritten
+        let head = (address.wrapping_neg() % 4).min(length);
+        let body = (length - head) & !0x3;
+
+        let (head_buffer, rest) = buffer.split_at_mut(head);
+        let (body_buffer, tail_buffer) = rest.split_at_mut(body);
 
-            peripherals
-                .APF_BRIDGE
-                .slot_id
-                .write(|w| w.bits(bridge_slot_id));
+        self.read_word(bridge_slot_id, offset, head_buffer)?;
 
-            // Ensure slot change and size read has occured, as it takes several cycles
-            peripherals.APF_BRIDGE.slot_id.read().bits();
+        if body > 0 {
+            self.request_read(
+                offset + head as u32,
+                body as u32,
+                body_buffer.as_mut_ptr() as usize,
+                bridge_slot_id,
+            )
+            .wait()?;
 
-            peripherals.APF_BRIDGE.file_size.read().bits()
+            // The bridge wrote behind the compiler's back
+            compiler_fence(Ordering::SeqCst);
         }
+
+        self.read_word(bridge_slot_id, offset + (head + body) as u32, tail_buffer)?;
+
+        Ok(length)
     }
 
     ///
-    /// Returns true when operation complete, false when operation ongoing
+    /// Writes `data` to `offset` of slot `bridge_slot_id`, blocking until complete
     ///
-    pub fn check_op_complete() -> bool {
-        unsafe {
-            let peripherals = pac::Peripherals::steal();
+    /// **NOTE:** Writes appear to be broken in the Pocket firmware at the moment. See `docs/control.md`
+    ///
+    pub fn write_from(
+        &mut self,
+        bridge_slot_id: u32,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<(), BridgeError> {
+        let address = data.as_ptr() as usize;
+
+        if address & 0x3 == 0 && self.backend.can_access(address, data.len()) {
+            // The bridge can read straight from `data`
+            compiler_fence(Ordering::SeqCst);
+
+            if !data.is_empty() {
+                self.request_write(offset, data.len() as u32, address, bridge_slot_id)
+                    .wait()?;
+            }
+
+            return Ok(());
+        }
+
+        // The bridge reads whole words from word aligned addresses, so stage unaligned data through an aligned buffer
+        let mut staging = [0u32; WRITE_STAGING_WORDS];
+
+        for (index, chunk) in data.chunks(WRITE_STAGING_WORDS * 4).enumerate() {
+            for (word, bytes) in staging.iter_mut().zip(chunk.chunks(4)) {
+                let mut padded = [0u8; 4];
+                padded[..bytes.len()].copy_from_slice(bytes);
+
+                *word = u32::from_le_bytes(padded);
+            }
+
+            compiler_fence(Ordering::SeqCst);
 
-            peripherals.APF_BRIDGE.status.read().bits() == 1
+            self.request_write(
+                offset + (index * WRITE_STAGING_WORDS * 4) as u32,
+                chunk.len() as u32,
+                staging.as_ptr() as usize,
+                bridge_slot_id,
+            )
+            .wait()?;
         }
+
+        Ok(())
+    }
+
+    ///
+    /// Reads up to 4 bytes into `buffer` by way of an aligned word
+    ///
+    fn read_word(
+        &mut self,
+        bridge_slot_id: u32,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<(), BridgeError> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut word = 0u32;
+
+        self.request_read(
+            offset,
+            buffer.len() as u32,
+            &mut word as *mut u32 as usize,
+            bridge_slot_id,
+        )
+        .wait()?;
+
+        compiler_fence(Ordering::SeqCst);
+
+        let bytes = unsafe { core::ptr::read_volatile(&word) }.to_le_bytes();
+        buffer.copy_from_slice(&bytes[..buffer.len()]);
+
+        Ok(())
+    }
+
+    pub fn size(&mut self, bridge_slot_id: u32) -> u32 {
+        self.backend.file_size(bridge_slot_id)
+    }
+
+    ///
+    /// Updates the size the Pocket records for the file in slot `bridge_slot_id`. Never do this for a read only slot,
+    /// as it will break future reads
+    ///
+    pub fn set_size(&mut self, bridge_slot_id: u32, size: u32) {
+        self.backend.set_file_size(bridge_slot_id, size);
+    }
+
+    ///
+    /// The RAM address the bridge is currently operating on
+    ///
+    pub fn current_address(&self) -> u32 {
+        self.backend.current_address()
+    }
+
+    ///
+    /// The raw result code of the most recently completed bridge command. 0 is success
+    ///
+    pub fn command_result_code(&self) -> u8 {
+        self.backend.command_result_code()
+    }
+
+    ///
+    /// Returns true when operation complete, false when operation ongoing. The completion flag is cleared on read
+    ///
+    pub fn check_op_complete(&self) -> bool {
+        self.backend.check_op_complete()
     }
 
-    pub fn block_op_complete() {
-        while !File::check_op_complete() {
+    pub fn block_op_complete(&self) {
+        while !self.check_op_complete() {
             // Loop
         }
     }
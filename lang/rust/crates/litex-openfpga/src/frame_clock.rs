@@ -0,0 +1,178 @@
+use crate::{frames_since, Video, FRAME_COUNTER_MASK};
+
+/// Paces a game loop to the display, one iteration per vblank.
+///
+/// Each `wait_for_vblank` measures the frames displayed since the last, from `VideoStatus::frame_counter`. More than 1
+/// means the loop overran and frames were dropped, which is counted so it can be reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameClock {
+    last_frame: Option<u32>,
+    delta: u32,
+    frames: u64,
+    dropped: u64,
+}
+
+impl FrameClock {
+    pub const fn new() -> Self {
+        FrameClock {
+            last_frame: None,
+            delta: 0,
+            frames: 0,
+            dropped: 0,
+        }
+    }
+
+    ///
+    /// Busy waits until the next vblank starts, then ticks. Returns immediately if vblank is already in progress and
+    /// this frame hasn't been ticked yet, so a `vblank_triggered` consumed elsewhere isn't missed. Returns the frame
+    /// delta
+    ///
+    pub fn wait_for_vblank(&mut self, video: &Video) -> u32 {
+        loop {
+            let status = video.status();
+
+            if status.vblank_triggered
+                || (status.vblank && self.last_frame != Some(status.frame_counter))
+            {
+                return self.tick(status.frame_counter);
+            }
+        }
+    }
+
+    ///
+    /// Advances to `frame_counter`, returning the frames displayed since the last tick. The first tick is always 1
+    /// frame, and repeated ticks within a frame are 0. `wait_for_vblank` calls this with the hardware state
+    ///
+    pub fn tick(&mut self, frame_counter: u32) -> u32 {
+        let frame_counter = frame_counter & FRAME_COUNTER_MASK;
+        let delta = frames_since(self.last_frame, frame_counter);
+
+        self.last_frame = Some(frame_counter);
+        self.delta = delta;
+        self.frames += delta as u64;
+        self.dropped += delta.saturating_sub(1) as u64;
+
+        delta
+    }
+
+    ///
+    /// The frames displayed between the last two ticks. 1 when the loop is keeping up
+    ///
+    pub fn delta(&self) -> u32 {
+        self.delta
+    }
+
+    ///
+    /// The `VideoStatus::frame_counter` at the last tick
+    ///
+    pub fn frame_counter(&self) -> u32 {
+        self.last_frame.unwrap_or(0)
+    }
+
+    ///
+    /// The frames displayed since the first tick, including that one
+    ///
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    ///
+    /// True if frames were dropped between the last two ticks
+    ///
+    pub fn missed_frame(&self) -> bool {
+        self.delta > 1
+    }
+
+    ///
+    /// The total number of frames dropped because the loop overran
+    ///
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn reset_dropped_frames(&mut self) {
+        self.dropped = 0;
+    }
+}
+
+/// Runs game logic at a fixed rate, independent of the display rate and any dropped frames.
+///
+/// Each frame, `advance` by the frame delta and run the returned number of logic steps. Steps are capped at
+/// `max_steps` per frame, so a long stall slows the game down rather than freezing it while it catches up.
+///
+/// ```ignore
+/// let mut timestep = FixedTimestep::new(120, 60);
+///
+/// loop {
+///     let delta = clock.wait_for_vblank(&pocket.video);
+///
+///     for _ in 0..timestep.advance(delta) {
+///         // Update physics
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedTimestep {
+    rate: u32,
+    frame_rate: u32,
+    max_steps: u32,
+    // Progress towards the next step, in units of 1 / (`rate` * `frame_rate`) seconds
+    accumulator: u32,
+}
+
+impl FixedTimestep {
+    /// The default cap on steps per `advance`
+    pub const DEFAULT_MAX_STEPS: u32 = 8;
+
+    ///
+    /// Steps `rate` times per second, on a display refreshing at `frame_rate` Hz
+    ///
+    pub const fn new(rate: u32, frame_rate: u32) -> Self {
+        assert!(
+            rate != 0 && frame_rate != 0,
+            "Timestep rates must be non-zero"
+        );
+
+        FixedTimestep {
+            rate,
+            frame_rate,
+            max_steps: Self::DEFAULT_MAX_STEPS,
+            accumulator: 0,
+        }
+    }
+
+    pub const fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    ///
+    /// Advances by `frames` displayed frames, returning the number of logic steps to run
+    ///
+    pub fn advance(&mut self, frames: u32) -> u32 {
+        let total = self.accumulator as u64 + frames as u64 * self.rate as u64;
+        let steps = total / self.frame_rate as u64;
+
+        if steps > self.max_steps as u64 {
+            // Drop the time we can't catch up on, keeping the fraction towards the next step
+            self.accumulator = (total % self.frame_rate as u64) as u32;
+
+            self.max_steps
+        } else {
+            self.accumulator = (total - steps * self.frame_rate as u64) as u32;
+
+            steps as u32
+        }
+    }
+
+    ///
+    /// How far between the last step and the next the display is, from 0.0 to 1.0. Use to interpolate rendering
+    ///
+    pub fn alpha(&self) -> f32 {
+        self.accumulator as f32 / self.frame_rate as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0;
+    }
+}
@@ -0,0 +1,618 @@
+use crate::Rgb565;
+
+/// A rectangle of pixels. The right and bottom edges are exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    ///
+    /// The rectangle from `(left, top)` up to, but not including, `(right, bottom)`. Empty if the corners are reversed
+    ///
+    pub const fn from_corners(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Rect {
+            x: left,
+            y: top,
+            width: if right > left {
+                (right - left) as u32
+            } else {
+                0
+            },
+            height: if bottom > top {
+                (bottom - top) as u32
+            } else {
+                0
+            },
+        }
+    }
+
+    ///
+    /// The first column to the right of the rectangle
+    ///
+    pub const fn right(&self) -> i32 {
+        self.x.saturating_add(self.width as i32)
+    }
+
+    ///
+    /// The first row below the rectangle
+    ///
+    pub const fn bottom(&self) -> i32 {
+        self.y.saturating_add(self.height as i32)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub const fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    ///
+    /// The area covered by both rectangles, which may be empty
+    ///
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        Rect::from_corners(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.right().min(other.right()),
+            self.bottom().min(other.bottom()),
+        )
+    }
+
+    ///
+    /// The smallest rectangle covering both. Empty rectangles are ignored
+    ///
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+
+        if other.is_empty() {
+            return *self;
+        }
+
+        Rect::from_corners(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.right().max(other.right()),
+            self.bottom().max(other.bottom()),
+        )
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    pub const fn offset(&self, dx: i32, dy: i32) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+}
+
+/// How shapes combine with the pixels already drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrawMode {
+    /// Overwrite the destination
+    #[default]
+    Replace,
+    /// Exclusive or with the destination. Drawing the same shape twice erases it
+    Xor,
+}
+
+/// How `Canvas::blit` combines source pixels with the destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlitMode {
+    /// Copy every pixel
+    Copy,
+    /// Copy every pixel except those of the key color, which are transparent
+    ColorKey(Rgb565),
+    /// Exclusive or every pixel with the destination
+    Xor,
+}
+
+/// Mirroring applied by `Canvas::blit_region_flipped`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Flip {
+    /// Mirror left to right
+    pub horizontal: bool,
+    /// Mirror top to bottom
+    pub vertical: bool,
+}
+
+impl Flip {
+    pub const NONE: Flip = Flip {
+        horizontal: false,
+        vertical: false,
+    };
+    pub const HORIZONTAL: Flip = Flip {
+        horizontal: true,
+        vertical: false,
+    };
+    pub const VERTICAL: Flip = Flip {
+        horizontal: false,
+        vertical: true,
+    };
+    pub const BOTH: Flip = Flip {
+        horizontal: true,
+        vertical: true,
+    };
+}
+
+/// Drawing on a buffer of RGB565 pixels, such as `Framebuffers::back_buffer`. Everything is clipped to the clip
+/// rectangle, which defaults to the whole canvas.
+///
+/// Shapes are drawn with the current `DrawMode`. Every pixel of a shape is drawn exactly once, so `DrawMode::Xor` can
+/// erase a shape by drawing it again.
+pub struct Canvas<'a> {
+    pixels: &'a mut [Rgb565],
+    width: usize,
+    height: usize,
+    mode: DrawMode,
+    clip: Rect,
+}
+
+impl<'a> Canvas<'a> {
+    ///
+    /// A canvas over `pixels`, `width` pixels per row. `pixels` must hold at least `width * height` pixels
+    ///
+    pub fn new(pixels: &'a mut [Rgb565], width: usize, height: usize) -> Self {
+        assert!(
+            pixels.len() >= width * height,
+            "Canvas of {width}x{height} needs {} pixels, but only has {}",
+            width * height,
+            pixels.len()
+        );
+
+        Canvas {
+            pixels,
+            width,
+            height,
+            mode: DrawMode::Replace,
+            clip: Rect::new(0, 0, width as u32, height as u32),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    ///
+    /// The area of the whole canvas
+    ///
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width as u32, self.height as u32)
+    }
+
+    ///
+    /// The area drawing is limited to
+    ///
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+
+    ///
+    /// Limits drawing to `clip`, within the canvas
+    ///
+    pub fn set_clip(&mut self, clip: Rect) {
+        self.clip = clip.intersection(&self.bounds());
+    }
+
+    pub fn reset_clip(&mut self) {
+        self.clip = self.bounds();
+    }
+
+    pub fn pixels(&self) -> &[Rgb565] {
+        &self.pixels[..self.width * self.height]
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Rgb565] {
+        &mut self.pixels[..self.width * self.height]
+    }
+
+    pub fn mode(&self) -> DrawMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: DrawMode) {
+        self.mode = mode;
+    }
+
+    ///
+    /// Fills the entire canvas with `color`, regardless of the draw mode and clip
+    ///
+    pub fn clear(&mut self, color: Rgb565) {
+        self.pixels_mut().fill(color);
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Option<Rgb565> {
+        self.index(x, y).map(|index| self.pixels[index])
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Rgb565) {
+        if let Some(index) = self.index(x, y) {
+            self.plot(index, color);
+        }
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Rgb565) {
+        let rect = rect.intersection(&self.clip);
+
+        for y in rect.y..rect.bottom() {
+            self.span(rect.x, rect.right(), y, color);
+        }
+    }
+
+    ///
+    /// Draws the 1 pixel outline just inside `rect`
+    ///
+    pub fn draw_rect(&mut self, rect: Rect, color: Rgb565) {
+        if rect.is_empty() {
+            return;
+        }
+
+        let right = rect.right() - 1;
+        let bottom = rect.bottom() - 1;
+
+        self.span_clipped(rect.x, rect.right(), rect.y, color);
+
+        if bottom > rect.y {
+            self.span_clipped(rect.x, rect.right(), bottom, color);
+        }
+
+        for y in rect.y + 1..bottom {
+            self.set_pixel(rect.x, y, color);
+
+            if right > rect.x {
+                self.set_pixel(right, y, color);
+            }
+        }
+    }
+
+    ///
+    /// Draws a line from `(x0, y0)` to `(x1, y1)`, including both ends
+    ///
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Rgb565) {
+        let Some((x0, y0, x1, y1)) = clip_line(self.clip, x0, y0, x1, y1) else {
+            return;
+        };
+
+        if y0 == y1 {
+            self.span_clipped(x0.min(x1), x0.max(x1) + 1, y0, color);
+
+            return;
+        }
+
+        // Bresenham's algorithm
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+
+        loop {
+            self.set_pixel(x, y, color);
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let doubled = 2 * error;
+
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    ///
+    /// Draws the 1 pixel outline of a circle, the edge of the circle `fill_circle` draws
+    ///
+    pub fn draw_circle(&mut self, center_x: i32, center_y: i32, radius: u32, color: Rgb565) {
+        let center_x = center_x as i64;
+
+        // Only the rows within the clip, so a huge circle costs no more than a small one
+        for (y, half_width) in self.circle_rows(center_y, radius) {
+            let dy = (y - center_y as i64).unsigned_abs();
+            // The ends of the row, and anything not covered by the row beyond it
+            let inner = match circle_half_width(radius, dy + 1) {
+                Some(next) => (next + 1).min(half_width),
+                None => 0,
+            };
+
+            if inner == 0 {
+                self.span_wide(center_x - half_width, center_x + half_width + 1, y, color);
+            } else {
+                self.span_wide(center_x - half_width, center_x - inner + 1, y, color);
+                self.span_wide(center_x + inner, center_x + half_width + 1, y, color);
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, center_x: i32, center_y: i32, radius: u32, color: Rgb565) {
+        let center_x = center_x as i64;
+
+        for (y, half_width) in self.circle_rows(center_y, radius) {
+            self.span_wide(center_x - half_width, center_x + half_width + 1, y, color);
+        }
+    }
+
+    ///
+    /// Draws all of `source`, an image `source_width` pixels wide, with its top left corner at `(x, y)`
+    ///
+    pub fn blit(&mut self, source: &[Rgb565], source_width: usize, x: i32, y: i32, mode: BlitMode) {
+        let source_height = source.len().checked_div(source_width).unwrap_or(0);
+
+        self.blit_region(
+            source,
+            source_width,
+            Rect::new(0, 0, source_width as u32, source_height as u32),
+            x,
+            y,
+            mode,
+        );
+    }
+
+    ///
+    /// Draws the `region` of `source`, an image `source_width` pixels wide, with the region's top left corner at
+    /// `(x, y)`. Suited to drawing one frame of a sprite sheet
+    ///
+    pub fn blit_region(
+        &mut self,
+        source: &[Rgb565],
+        source_width: usize,
+        region: Rect,
+        x: i32,
+        y: i32,
+        mode: BlitMode,
+    ) {
+        self.blit_region_flipped(source, source_width, region, x, y, mode, Flip::NONE);
+    }
+
+    ///
+    /// Draws the `region` of `source` like `blit_region`, mirrored by `flip`
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_region_flipped(
+        &mut self,
+        source: &[Rgb565],
+        source_width: usize,
+        region: Rect,
+        x: i32,
+        y: i32,
+        mode: BlitMode,
+        flip: Flip,
+    ) {
+        if source_width == 0 {
+            return;
+        }
+
+        let source_bounds = Rect::new(
+            0,
+            0,
+            source_width as u32,
+            (source.len() / source_width) as u32,
+        );
+        let clipped = region.intersection(&source_bounds);
+        // Where the clipped region lands, then clip that to the canvas and map back into the source
+        let origin_x = x + (clipped.x - region.x);
+        let origin_y = y + (clipped.y - region.y);
+        let destination =
+            Rect::new(origin_x, origin_y, clipped.width, clipped.height).intersection(&self.clip);
+
+        if destination.is_empty() {
+            return;
+        }
+
+        let width = destination.width as usize;
+        let relative_x = destination.x - origin_x;
+        // Mirrored rows are read from the opposite side, then reversed
+        let source_x = clipped.x
+            + if flip.horizontal {
+                clipped.width as i32 - relative_x - width as i32
+            } else {
+                relative_x
+            };
+
+        for row in 0..destination.height as i32 {
+            let relative_y = destination.y - origin_y + row;
+            let source_y = clipped.y
+                + if flip.vertical {
+                    clipped.height as i32 - 1 - relative_y
+                } else {
+                    relative_y
+                };
+            let start = source_y as usize * source_width + source_x as usize;
+            let source_row = &source[start..start + width];
+            let start = (destination.y + row) as usize * self.width + destination.x as usize;
+            let destination_row = &mut self.pixels[start..start + width];
+
+            match (mode, flip.horizontal) {
+                (BlitMode::Copy, false) => destination_row.copy_from_slice(source_row),
+                (_, false) => blend_row(destination_row, source_row.iter(), mode),
+                (_, true) => blend_row(destination_row, source_row.iter().rev(), mode),
+            }
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if !self.clip.contains(x, y) {
+            None
+        } else {
+            Some(y as usize * self.width + x as usize)
+        }
+    }
+
+    fn plot(&mut self, index: usize, color: Rgb565) {
+        match self.mode {
+            DrawMode::Replace => self.pixels[index] = color,
+            DrawMode::Xor => self.pixels[index].0 ^= color.0,
+        }
+    }
+
+    // Draws columns `left` up to `right` of row `y`, which must already be clipped
+    fn span(&mut self, left: i32, right: i32, y: i32, color: Rgb565) {
+        if left >= right {
+            return;
+        }
+
+        let start = y as usize * self.width;
+        let row = &mut self.pixels[start + left as usize..start + right as usize];
+
+        match self.mode {
+            DrawMode::Replace => row.fill(color),
+            DrawMode::Xor => {
+                for pixel in row {
+                    pixel.0 ^= color.0;
+                }
+            }
+        }
+    }
+
+    // Each row of a circle within the clip, with the half width of the circle on that row
+    fn circle_rows(&self, center_y: i32, radius: u32) -> impl Iterator<Item = (i64, i64)> {
+        let center_y = center_y as i64;
+        let top = (center_y - radius as i64).max(self.clip.y as i64);
+        let bottom = (center_y + radius as i64 + 1).min(self.clip.bottom() as i64);
+
+        (top..bottom).filter_map(move |y| {
+            circle_half_width(radius, (y - center_y).unsigned_abs())
+                .map(|half_width| (y, half_width))
+        })
+    }
+
+    // A span in a row within the clip, with ends that may be far outside of it
+    fn span_wide(&mut self, left: i64, right: i64, y: i64, color: Rgb565) {
+        let left = left.max(self.clip.x as i64);
+        let right = right.min(self.clip.right() as i64);
+
+        if left < right {
+            self.span(left as i32, right as i32, y as i32, color);
+        }
+    }
+
+    fn span_clipped(&mut self, left: i32, right: i32, y: i32, color: Rgb565) {
+        if y >= self.clip.y && y < self.clip.bottom() {
+            self.span(
+                left.max(self.clip.x),
+                right.min(self.clip.right()),
+                y,
+                color,
+            );
+        }
+    }
+}
+
+///
+/// The half width of a circle `dy` rows from its center, or None past its top and bottom. Slightly past the exact
+/// radius, which rounds off the flat edges
+///
+fn circle_half_width(radius: u32, dy: u64) -> Option<i64> {
+    let radius = radius as u64;
+
+    (dy <= radius).then(|| (radius * radius + radius - dy * dy).isqrt() as i64)
+}
+
+///
+/// Clips the line from `(x0, y0)` to `(x1, y1)` to `clip` with the Liang-Barsky algorithm, keeping its direction.
+/// None if no part of it is inside
+///
+fn clip_line(clip: Rect, x0: i32, y0: i32, x1: i32, y1: i32) -> Option<(i32, i32, i32, i32)> {
+    if clip.is_empty() {
+        return None;
+    }
+
+    let (x0, y0) = (x0 as i128, y0 as i128);
+    let (dx, dy) = (x1 as i128 - x0, y1 as i128 - y0);
+    let (left, top) = (clip.x as i128, clip.y as i128);
+    let (right, bottom) = (clip.right() as i128 - 1, clip.bottom() as i128 - 1);
+
+    // The part of the line inside is `t` from `enter` to `exit`, as fractions with positive denominators
+    let mut enter = (0, 1);
+    let mut exit = (1, 1);
+
+    // Each edge keeps the points where `p * t <= q`
+    for (p, q) in [
+        (-dx, x0 - left),
+        (dx, right - x0),
+        (-dy, y0 - top),
+        (dy, bottom - y0),
+    ] {
+        if p == 0 {
+            if q < 0 {
+                return None;
+            }
+        } else if p < 0 {
+            if -q * enter.1 > enter.0 * -p {
+                enter = (-q, -p);
+            }
+        } else if q * exit.1 < exit.0 * p {
+            exit = (q, p);
+        }
+    }
+
+    if enter.0 * exit.1 > exit.0 * enter.1 {
+        return None;
+    }
+
+    // Rounding to the nearest pixel stays inside, as the edges are whole pixels
+    let point = |(numerator, denominator): (i128, i128)| {
+        let offset =
+            |delta: i128| (2 * numerator * delta + denominator).div_euclid(2 * denominator);
+
+        ((x0 + offset(dx)) as i32, (y0 + offset(dy)) as i32)
+    };
+
+    let (start, end) = (point(enter), point(exit));
+
+    Some((start.0, start.1, end.0, end.1))
+}
+
+fn blend_row<'a>(
+    destination: &mut [Rgb565],
+    source: impl Iterator<Item = &'a Rgb565>,
+    mode: BlitMode,
+) {
+    match mode {
+        BlitMode::Copy => {
+            for (destination, &source) in destination.iter_mut().zip(source) {
+                *destination = source;
+            }
+        }
+        BlitMode::ColorKey(key) => {
+            for (destination, &source) in destination.iter_mut().zip(source) {
+                if source != key {
+                    *destination = source;
+                }
+            }
+        }
+        BlitMode::Xor => {
+            for (destination, &source) in destination.iter_mut().zip(source) {
+                destination.0 ^= source.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub};
+
+use litex_pac as pac;
+
+/// The number of controller ports, `cont1` through `cont4`
+pub const CONTROLLER_COUNT: usize = 4;
+
+/// A set of controller buttons, as laid out in the low 16 bits of `contN_key`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Buttons(u16);
+
+impl Buttons {
+    pub const DPAD_UP: Buttons = Buttons(1 << 0);
+    pub const DPAD_DOWN: Buttons = Buttons(1 << 1);
+    pub const DPAD_LEFT: Buttons = Buttons(1 << 2);
+    pub const DPAD_RIGHT: Buttons = Buttons(1 << 3);
+    pub const FACE_A: Buttons = Buttons(1 << 4);
+    pub const FACE_B: Buttons = Buttons(1 << 5);
+    pub const FACE_X: Buttons = Buttons(1 << 6);
+    pub const FACE_Y: Buttons = Buttons(1 << 7);
+    pub const TRIG_L1: Buttons = Buttons(1 << 8);
+    pub const TRIG_R1: Buttons = Buttons(1 << 9);
+    pub const TRIG_L2: Buttons = Buttons(1 << 10);
+    pub const TRIG_R2: Buttons = Buttons(1 << 11);
+    pub const TRIG_L3: Buttons = Buttons(1 << 12);
+    pub const TRIG_R3: Buttons = Buttons(1 << 13);
+    pub const FACE_SELECT: Buttons = Buttons(1 << 14);
+    pub const FACE_START: Buttons = Buttons(1 << 15);
+
+    /// Every direction of the dpad
+    pub const DPAD: Buttons = Buttons(0xF);
+
+    pub const fn empty() -> Self {
+        Buttons(0)
+    }
+
+    pub const fn all() -> Self {
+        Buttons(0xFFFF)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Buttons(bits)
+    }
+
+    ///
+    /// The buttons of a raw `contN_key` value, discarding the controller type
+    ///
+    pub const fn from_key(key: u32) -> Self {
+        Buttons(key as u16)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    ///
+    /// True if every button in `other` is in `self`
+    ///
+    pub const fn contains(self, other: Buttons) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    ///
+    /// True if any button in `other` is in `self`
+    ///
+    pub const fn intersects(self, other: Buttons) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub const fn union(self, other: Buttons) -> Self {
+        Buttons(self.0 | other.0)
+    }
+
+    pub fn insert(&mut self, other: Buttons) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Buttons) {
+        self.0 &= !other.0;
+    }
+
+    pub fn set(&mut self, other: Buttons, value: bool) {
+        if value {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
+    }
+}
+
+impl BitOr for Buttons {
+    type Output = Buttons;
+
+    fn bitor(self, rhs: Buttons) -> Buttons {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for Buttons {
+    fn bitor_assign(&mut self, rhs: Buttons) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Buttons {
+    type Output = Buttons;
+
+    fn bitand(self, rhs: Buttons) -> Buttons {
+        Buttons(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for Buttons {
+    fn bitand_assign(&mut self, rhs: Buttons) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl Sub for Buttons {
+    type Output = Buttons;
+
+    /// The buttons in `self` that aren't in `rhs`
+    fn sub(self, rhs: Buttons) -> Buttons {
+        Buttons(self.0 & !rhs.0)
+    }
+}
+
+impl Not for Buttons {
+    type Output = Buttons;
+
+    fn not(self) -> Buttons {
+        Buttons(!self.0)
+    }
+}
+
+/// The kind of controller attached to a port, from bits 31:29 of `contN_key`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControllerType {
+    /// Nothing is connected
+    None,
+    /// The Pocket's built in buttons
+    Pocket,
+    /// A docked gamepad without analog sticks or triggers
+    DockedGamepad,
+    /// A docked gamepad with analog sticks and triggers
+    DockedAnalogGamepad,
+    DockedKeyboard,
+    DockedMouse,
+    /// A type not known to this crate
+    Unknown(u8),
+}
+
+impl ControllerType {
+    ///
+    /// Decodes the controller type from a raw `contN_key` value
+    ///
+    pub const fn from_key(key: u32) -> Self {
+        match (key >> 29) as u8 {
+            0 => ControllerType::None,
+            1 => ControllerType::Pocket,
+            2 => ControllerType::DockedGamepad,
+            3 => ControllerType::DockedAnalogGamepad,
+            4 => ControllerType::DockedKeyboard,
+            5 => ControllerType::DockedMouse,
+            other => ControllerType::Unknown(other),
+        }
+    }
+
+    pub const fn is_connected(self) -> bool {
+        !matches!(self, ControllerType::None)
+    }
+}
+
+/// Both analog sticks, from `contN_joy`. Each axis is unsigned, and centered at `0x80`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Joystick {
+    pub lstick_x: u8,
+    pub lstick_y: u8,
+    pub rstick_x: u8,
+    pub rstick_y: u8,
+}
+
+impl Joystick {
+    /// The value of an axis at rest
+    pub const CENTER: u8 = 0x80;
+
+    /// Both sticks at rest
+    pub const CENTERED: Joystick = Joystick {
+        lstick_x: Joystick::CENTER,
+        lstick_y: Joystick::CENTER,
+        rstick_x: Joystick::CENTER,
+        rstick_y: Joystick::CENTER,
+    };
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Joystick {
+            lstick_x: bits as u8,
+            lstick_y: (bits >> 8) as u8,
+            rstick_x: (bits >> 16) as u8,
+            rstick_y: (bits >> 24) as u8,
+        }
+    }
+
+    pub const fn to_bits(self) -> u32 {
+        (self.lstick_x as u32)
+            | ((self.lstick_y as u32) << 8)
+            | ((self.rstick_x as u32) << 16)
+            | ((self.rstick_y as u32) << 24)
+    }
+}
+
+impl Default for Joystick {
+    fn default() -> Self {
+        Joystick::CENTERED
+    }
+}
+
+/// Both analog triggers, from `contN_trig`. On the Pocket itself, these are only ever fully released or pressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Triggers {
+    pub left: u8,
+    pub right: u8,
+}
+
+impl Triggers {
+    pub const fn from_bits(bits: u32) -> Self {
+        Triggers {
+            left: bits as u8,
+            right: (bits >> 8) as u8,
+        }
+    }
+
+    pub const fn to_bits(self) -> u32 {
+        (self.left as u32) | ((self.right as u32) << 8)
+    }
+}
+
+/// A snapshot of everything a controller port reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Controller {
+    pub controller_type: ControllerType,
+    pub buttons: Buttons,
+    pub joystick: Joystick,
+    pub triggers: Triggers,
+}
+
+impl Controller {
+    ///
+    /// Reads controller `index`, from 0 to 3
+    ///
+    pub fn read<S: InputSource + ?Sized>(source: &S, index: usize) -> Self {
+        Controller::from_bits(source.key(index), source.joy(index), source.trig(index))
+    }
+
+    ///
+    /// Decodes raw `contN_key`, `contN_joy`, and `contN_trig` values
+    ///
+    pub const fn from_bits(key: u32, joy: u32, trig: u32) -> Self {
+        Controller {
+            controller_type: ControllerType::from_key(key),
+            buttons: Buttons::from_key(key),
+            joystick: Joystick::from_bits(joy),
+            triggers: Triggers::from_bits(trig),
+        }
+    }
+
+    pub const fn is_connected(&self) -> bool {
+        self.controller_type.is_connected()
+    }
+}
+
+/// The raw `APF_INPUT` registers of every controller, captured at one instant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InputSnapshot {
+    pub key: [u32; CONTROLLER_COUNT],
+    pub joy: [u32; CONTROLLER_COUNT],
+    pub trig: [u32; CONTROLLER_COUNT],
+}
+
+impl InputSnapshot {
+    pub fn controller(&self, index: usize) -> Controller {
+        Controller::read(self, index)
+    }
+}
+
+/// Anything that provides the `APF_INPUT` registers. Implemented by `Input` for the live hardware, and by
+/// `InputSnapshot` and `InputPlayback` so recorded input can stand in for it.
+pub trait InputSource {
+    ///
+    /// The raw `contN_key` button bitmap for the given controller
+    ///
+    fn key(&self, index: usize) -> u32;
+
+    ///
+    /// The raw `contN_joy` joystick bitmap for the given controller
+    ///
+    fn joy(&self, index: usize) -> u32;
+
+    ///
+    /// The raw `contN_trig` trigger bitmap for the given controller
+    ///
+    fn trig(&self, index: usize) -> u32;
+
+    ///
+    /// Captures every register of every controller
+    ///
+    fn snapshot(&self) -> InputSnapshot {
+        let mut snapshot = InputSnapshot::default();
+
+        for index in 0..CONTROLLER_COUNT {
+            snapshot.key[index] = self.key(index);
+            snapshot.joy[index] = self.joy(index);
+            snapshot.trig[index] = self.trig(index);
+        }
+
+        snapshot
+    }
+}
+
+impl InputSource for InputSnapshot {
+    fn key(&self, index: usize) -> u32 {
+        self.key[index]
+    }
+
+    fn joy(&self, index: usize) -> u32 {
+        self.joy[index]
+    }
+
+    fn trig(&self, index: usize) -> u32 {
+        self.trig[index]
+    }
+
+    fn snapshot(&self) -> InputSnapshot {
+        *self
+    }
+}
+
+/// Owned access to the `APF_INPUT` block.
+///
+/// Controllers are indexed from 0 to 3, corresponding to `cont1` through `cont4`.
+pub struct Input {
+    registers: pac::APF_INPUT,
+}
+
+impl Input {
+    pub fn new(registers: pac::APF_INPUT) -> Self {
+        Input { registers }
+    }
+
+    pub fn free(self) -> pac::APF_INPUT {
+        self.registers
+    }
+
+    ///
+    /// The currently held buttons of the given controller
+    ///
+    pub fn buttons(&self, index: usize) -> Buttons {
+        Buttons::from_key(self.key(index))
+    }
+
+    ///
+    /// The raw `contN_key` button bitmap for the given controller
+    ///
+    pub fn key(&self, index: usize) -> u32 {
+        match index {
+            0 => self.registers.cont1_key.read().bits(),
+            1 => self.registers.cont2_key.read().bits(),
+            2 => self.registers.cont3_key.read().bits(),
+            3 => self.registers.cont4_key.read().bits(),
+            _ => panic!("Controller index {index} out of range"),
+        }
+    }
+
+    ///
+    /// The raw `contN_joy` joystick bitmap for the given controller
+    ///
+    pub fn joy(&self, index: usize) -> u32 {
+        match index {
+            0 => self.registers.cont1_joy.read().bits(),
+            1 => self.registers.cont2_joy.read().bits(),
+            2 => self.registers.cont3_joy.read().bits(),
+            3 => self.registers.cont4_joy.read().bits(),
+            _ => panic!("Controller index {index} out of range"),
+        }
+    }
+
+    ///
+    /// The raw `contN_trig` trigger bitmap for the given controller
+    ///
+    pub fn trig(&self, index: usize) -> u32 {
+        match index {
+            0 => self.registers.cont1_trig.read().bits(),
+            1 => self.registers.cont2_trig.read().bits(),
+            2 => self.registers.cont3_trig.read().bits(),
+            3 => self.registers.cont4_trig.read().bits(),
+            _ => panic!("Controller index {index} out of range"),
+        }
+    }
+}
+
+impl InputSource for Input {
+    fn key(&self, index: usize) -> u32 {
+        Input::key(self, index)
+    }
+
+    fn joy(&self, index: usize) -> u32 {
+        Input::joy(self, index)
+    }
+
+    fn trig(&self, index: usize) -> u32 {
+        Input::trig(self, index)
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    frames_since, Analog, Buttons, Controller, InputSource, Joystick, Stick, TriggerLevels,
+    CONTROLLER_COUNT,
+};
+
+/// The default number of frames a button is held before it starts repeating
+pub const DEFAULT_REPEAT_DELAY: u32 = 20;
+/// The default number of frames between repeats, once repeating
+pub const DEFAULT_REPEAT_RATE: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct ControllerState {
+    controller: Controller,
+    analog: Analog,
+    held: Buttons,
+    previous: Buttons,
+    repeated: Buttons,
+    // Frames each button has been held for, by bit index
+    held_frames: [u32; 16],
+}
+
+impl ControllerState {
+    const fn new() -> Self {
+        ControllerState {
+            controller: Controller::from_bits(0, Joystick::CENTERED.to_bits(), 0),
+            analog: Analog::new(),
+            held: Buttons::empty(),
+            previous: Buttons::empty(),
+            repeated: Buttons::empty(),
+            held_frames: [0; 16],
+        }
+    }
+}
+
+/// Per frame button state for every controller, with edge detection and auto-repeat.
+///
+/// Call `update` once per frame, then query. Queries taking a set of `Buttons` are true if any button in the set
+/// matches.
+pub struct InputState {
+    controllers: [ControllerState; CONTROLLER_COUNT],
+    last_frame: Option<u32>,
+    repeat_delay: u32,
+    repeat_rate: u32,
+}
+
+impl InputState {
+    pub const fn new() -> Self {
+        InputState {
+            controllers: [ControllerState::new(); CONTROLLER_COUNT],
+            last_frame: None,
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_rate: DEFAULT_REPEAT_RATE,
+        }
+    }
+
+    ///
+    /// Sets the auto-repeat timing, in frames. A `rate` of 0 disables repeating
+    ///
+    pub const fn with_repeat(mut self, delay: u32, rate: u32) -> Self {
+        self.repeat_delay = delay;
+        self.repeat_rate = rate;
+        self
+    }
+
+    pub fn set_repeat(&mut self, delay: u32, rate: u32) {
+        self.repeat_delay = delay;
+        self.repeat_rate = rate;
+    }
+
+    ///
+    /// Samples every controller from `input`, usually `Input` or an `InputPlayback`, given the current
+    /// `VideoStatus::frame_counter`. Hold times advance by the number of frames displayed since the last update, and
+    /// repeated calls within a single frame are ignored
+    ///
+    pub fn update<S: InputSource + ?Sized>(&mut self, input: &S, frame_counter: u32) {
+        let frames = frames_since(self.last_frame, frame_counter);
+
+        if frames == 0 {
+            return;
+        }
+
+        self.last_frame = Some(frame_counter);
+
+        let mut buttons = [Buttons::empty(); CONTROLLER_COUNT];
+
+        for (index, (state, buttons)) in self.controllers.iter_mut().zip(&mut buttons).enumerate() {
+            state.controller = Controller::read(input, index);
+            *buttons = state.analog.buttons(&state.controller);
+        }
+
+        self.update_buttons(buttons, frames);
+    }
+
+    ///
+    /// Advances `frames` frames with the given held buttons for each controller. `update` calls this with the hardware
+    /// state, but it can also be driven directly, such as when replaying input
+    ///
+    pub fn update_buttons(&mut self, buttons: [Buttons; CONTROLLER_COUNT], frames: u32) {
+        let frames = frames.max(1);
+        let delay = self.repeat_delay;
+        let rate = self.repeat_rate;
+
+        for (state, buttons) in self.controllers.iter_mut().zip(buttons) {
+            state.previous = state.held;
+            state.held = buttons;
+            state.repeated = Buttons::empty();
+
+            for (bit, held_frames) in state.held_frames.iter_mut().enumerate() {
+                let button = Buttons::from_bits(1 << bit);
+
+                if !buttons.contains(button) {
+                    *held_frames = 0;
+                    continue;
+                }
+
+                let before = *held_frames;
+                let after = if before == 0 {
+                    1
+                } else {
+                    before.saturating_add(frames)
+                };
+
+                if repeats(after, delay, rate) > repeats(before, delay, rate) {
+                    state.repeated.insert(button);
+                }
+
+                *held_frames = after;
+            }
+        }
+    }
+
+    ///
+    /// Forgets all held buttons, such as when switching screens, so nothing reads as pressed until released
+    ///
+    pub fn clear(&mut self) {
+        for state in &mut self.controllers {
+            *state = ControllerState {
+                analog: state.analog,
+                ..ControllerState::new()
+            };
+        }
+    }
+
+    ///
+    /// The stick processing for controller `index`, including whether its left stick emulates the dpad
+    ///
+    pub fn analog(&self, index: usize) -> &Analog {
+        &self.controllers[index].analog
+    }
+
+    pub fn analog_mut(&mut self, index: usize) -> &mut Analog {
+        &mut self.controllers[index].analog
+    }
+
+    ///
+    /// The raw state of controller `index` as of the last `update`
+    ///
+    pub fn controller(&self, index: usize) -> &Controller {
+        &self.controllers[index].controller
+    }
+
+    pub fn left_stick(&self, index: usize) -> Stick {
+        let state = &self.controllers[index];
+
+        state.analog.left_stick(state.controller.joystick)
+    }
+
+    pub fn right_stick(&self, index: usize) -> Stick {
+        let state = &self.controllers[index];
+
+        state.analog.right_stick(state.controller.joystick)
+    }
+
+    pub fn triggers(&self, index: usize) -> TriggerLevels {
+        let state = &self.controllers[index];
+
+        state.analog.triggers(state.controller.triggers)
+    }
+
+    ///
+    /// The buttons currently held on controller `index`
+    ///
+    pub fn held(&self, index: usize) -> Buttons {
+        self.controllers[index].held
+    }
+
+    pub fn is_held(&self, index: usize, buttons: Buttons) -> bool {
+        self.held(index).intersects(buttons)
+    }
+
+    ///
+    /// True if a button in `buttons` went down in the last update
+    ///
+    pub fn just_pressed(&self, index: usize, buttons: Buttons) -> bool {
+        let state = &self.controllers[index];
+
+        (state.held - state.previous).intersects(buttons)
+    }
+
+    ///
+    /// True if a button in `buttons` went up in the last update
+    ///
+    pub fn just_released(&self, index: usize, buttons: Buttons) -> bool {
+        let state = &self.controllers[index];
+
+        (state.previous - state.held).intersects(buttons)
+    }
+
+    ///
+    /// The number of frames the longest held button in `buttons` has been down, counting the frame it was pressed as 1.
+    /// 0 if none are held
+    ///
+    pub fn held_frames(&self, index: usize, buttons: Buttons) -> u32 {
+        let state = &self.controllers[index];
+
+        (0..16)
+            .filter(|bit| buttons.contains(Buttons::from_bits(1 << bit)))
+            .map(|bit| state.held_frames[bit])
+            .max()
+            .unwrap_or(0)
+    }
+
+    ///
+    /// True if a button in `buttons` was just pressed, or has been held long enough to auto-repeat in the last update.
+    /// Suited to menu navigation
+    ///
+    pub fn repeated(&self, index: usize, buttons: Buttons) -> bool {
+        self.controllers[index].repeated.intersects(buttons)
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState::new()
+    }
+}
+
+// The number of times a button held for `held_frames` has fired, including the initial press
+fn repeats(held_frames: u32, delay: u32, rate: u32) -> u32 {
+    if held_frames == 0 {
+        0
+    } else if rate == 0 || held_frames <= delay {
+        1
+    } else {
+        2 + (held_frames - delay - 1) / rate
+    }
+}
@@ -0,0 +1,362 @@
+use litex_pac as pac;
+
+/// The number of `interact.json` values exposed by the core, from `0x1000_0100` to `0x1000_0140`
+pub const INTERACT_COUNT: usize = 16;
+
+/// Owned access to the `APF_INTERACT` block.
+pub struct Interact {
+    registers: pac::APF_INTERACT,
+}
+
+// The PAC exposes each index as a separately typed register, so expand `$body` for the matching pair
+macro_rules! with_interact {
+    ($registers:expr, $index:expr, |$value:ident, $changed:ident| $body:expr) => {
+        match $index {
+            0 => {
+                let $value = &$registers.interact0;
+                let $changed = &$registers.interact_changed0;
+                $body
+            }
+            1 => {
+                let $value = &$registers.interact1;
+                let $changed = &$registers.interact_changed1;
+                $body
+            }
+            2 => {
+                let $value = &$registers.interact2;
+                let $changed = &$registers.interact_changed2;
+                $body
+            }
+            3 => {
+                let $value = &$registers.interact3;
+                let $changed = &$registers.interact_changed3;
+                $body
+            }
+            4 => {
+                let $value = &$registers.interact4;
+                let $changed = &$registers.interact_changed4;
+                $body
+            }
+            5 => {
+                let $value = &$registers.interact5;
+                let $changed = &$registers.interact_changed5;
+                $body
+            }
+            6 => {
+                let $value = &$registers.interact6;
+                let $changed = &$registers.interact_changed6;
+                $body
+            }
+            7 => {
+                let $value = &$registers.interact7;
+                let $changed = &$registers.interact_changed7;
+                $body
+            }
+            8 => {
+                let $value = &$registers.interact8;
+                let $changed = &$registers.interact_changed8;
+                $body
+            }
+            9 => {
+                let $value = &$registers.interact9;
+                let $changed = &$registers.interact_changed9;
+                $body
+            }
+            10 => {
+                let $value = &$registers.interact10;
+                let $changed = &$registers.interact_changed10;
+                $body
+            }
+            11 => {
+                let $value = &$registers.interact11;
+                let $changed = &$registers.interact_changed11;
+                $body
+            }
+            12 => {
+                let $value = &$registers.interact12;
+                let $changed = &$registers.interact_changed12;
+                $body
+            }
+            13 => {
+                let $value = &$registers.interact13;
+                let $changed = &$registers.interact_changed13;
+                $body
+            }
+            14 => {
+                let $value = &$registers.interact14;
+                let $changed = &$registers.interact_changed14;
+                $body
+            }
+            15 => {
+                let $value = &$registers.interact15;
+                let $changed = &$registers.interact_changed15;
+                $body
+            }
+            index => panic!("Interact index {index} out of range"),
+        }
+    };
+}
+
+impl Interact {
+    pub fn new(registers: pac::APF_INTERACT) -> Self {
+        Interact { registers }
+    }
+
+    pub fn free(self) -> pac::APF_INTERACT {
+        self.registers
+    }
+
+    ///
+    /// The current value of `interact.json` entry `index`
+    ///
+    pub fn get(&self, index: usize) -> u32 {
+        with_interact!(self.registers, index, |value, _changed| value.read().bits())
+    }
+
+    pub fn set(&mut self, index: usize, value: u32) {
+        with_interact!(self.registers, index, |register, _changed| unsafe {
+            register.write(|w| w.bits(value))
+        })
+    }
+
+    ///
+    /// Returns true when the host has updated entry `index`. Reading clears the flag
+    ///
+    pub fn changed(&self, index: usize) -> bool {
+        with_interact!(self.registers, index, |_value, changed| changed
+            .read()
+            .bits()
+            != 0)
+    }
+
+    ///
+    /// Yields the index and new value of every entry the host has updated, clearing their flags. Flags are only read,
+    /// and cleared, as the iterator reaches them
+    ///
+    pub fn take_changed(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        (0..INTERACT_COUNT)
+            .filter(|&index| self.changed(index))
+            .map(|index| (index, self.get(index)))
+    }
+}
+
+/// A type that can be stored in an `interact.json` value.
+pub trait InteractValue: Sized {
+    fn from_interact(value: u32) -> Self;
+
+    fn to_interact(self) -> u32;
+}
+
+impl InteractValue for u32 {
+    fn from_interact(value: u32) -> Self {
+        value
+    }
+
+    fn to_interact(self) -> u32 {
+        self
+    }
+}
+
+impl InteractValue for i32 {
+    fn from_interact(value: u32) -> Self {
+        value as i32
+    }
+
+    fn to_interact(self) -> u32 {
+        self as u32
+    }
+}
+
+impl InteractValue for u16 {
+    fn from_interact(value: u32) -> Self {
+        value as u16
+    }
+
+    fn to_interact(self) -> u32 {
+        self as u32
+    }
+}
+
+impl InteractValue for u8 {
+    fn from_interact(value: u32) -> Self {
+        value as u8
+    }
+
+    fn to_interact(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Any non-zero value is true, so a `check` entry can use any `value`
+impl InteractValue for bool {
+    fn from_interact(value: u32) -> Self {
+        value != 0
+    }
+
+    fn to_interact(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Declares a fieldless enum stored in an `interact.json` value, such as a `list` or `radio` group. Unknown values
+/// decode as the first variant.
+///
+/// ```ignore
+/// interact_enum! {
+///     pub enum Difficulty {
+///         Easy = 0,
+///         Normal = 1,
+///         Hard = 2,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! interact_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $value:expr
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )+
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// Every variant, in declaration order
+            pub const VARIANTS: &'static [$name] = &[$($name::$variant),+];
+        }
+
+        impl $crate::InteractValue for $name {
+            fn from_interact(value: u32) -> Self {
+                $(
+                    if value == $value {
+                        return $name::$variant;
+                    }
+                )+
+
+                $name::VARIANTS[0]
+            }
+
+            fn to_interact(self) -> u32 {
+                match self {
+                    $($name::$variant => $value,)+
+                }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::VARIANTS[0]
+            }
+        }
+    };
+}
+
+/// Declares a struct of typed settings, each bound to an `interact.json` index. Field types implement
+/// `InteractValue`. An index can be any constant expression, so fields can be bound to the `InteractVariable`s that
+/// generate `interact.json` rather than restating their indices.
+///
+/// The struct gets `read` to load every field, `update` to reload only the fields the host has changed, and `write`
+/// to send every field back to the host.
+///
+/// ```ignore
+/// interact_settings! {
+///     pub struct Settings {
+///         pub sound[SOUND.index()]: bool,
+///         pub difficulty[DIFFICULTY.index()]: Difficulty,
+///         pub volume[4]: u32,
+///     }
+/// }
+///
+/// let mut settings = Settings::read(&pocket.interact);
+///
+/// loop {
+///     if settings.update(&pocket.interact) {
+///         // Apply the new settings
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! interact_settings {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident[$index:expr]: $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// The `interact.json` indices of every field, in declaration order
+            pub const INDICES: &'static [usize] = &[$($index),*];
+
+            ///
+            /// Reads every field from the host
+            ///
+            pub fn read(interact: &$crate::Interact) -> Self {
+                $name {
+                    $($field: $crate::InteractValue::from_interact(interact.get(const { $index })),)*
+                }
+            }
+
+            ///
+            /// Reloads every field the host has changed since the last check, returning true if any were. Only the
+            /// change flags of this struct's indices are cleared
+            ///
+            pub fn update(&mut self, interact: &$crate::Interact) -> bool {
+                let mut changed = false;
+
+                $(
+                    if interact.changed(const { $index }) {
+                        changed |= self.apply(const { $index }, interact.get(const { $index }));
+                    }
+                )*
+
+                changed
+            }
+
+            ///
+            /// Sends every field to the host
+            ///
+            pub fn write(&self, interact: &mut $crate::Interact) {
+                $(interact.set(const { $index }, $crate::InteractValue::to_interact(self.$field));)*
+            }
+
+            ///
+            /// Updates the field bound to `index` from a raw value, returning true if there is one
+            ///
+            pub fn apply(&mut self, index: usize, value: u32) -> bool {
+                $(
+                    if index == const { $index } {
+                        self.$field = $crate::InteractValue::from_interact(value);
+
+                        return true;
+                    }
+                )*
+
+                false
+            }
+        }
+    };
+}
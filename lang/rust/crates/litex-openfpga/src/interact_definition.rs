@@ -0,0 +1,488 @@
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use crate::{Interact, INTERACT_COUNT};
+
+/// The address of `APF_INTERACT` index 0, as seen by the Pocket
+pub const INTERACT_BASE_ADDRESS: u32 = 0x1000_0100;
+
+// Addresses below `INTERACT_BASE_ADDRESS` are handled by the core itself, such as resetting the CPU
+const CORE_BASE_ADDRESS: u32 = 0x1000_0000;
+
+/// Where the Pocket writes an `interact.json` variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractAddress {
+    /// An `APF_INTERACT` index, readable through `Interact`
+    Index(usize),
+    /// A register implemented by the core, from `0x1000_0000` up to `INTERACT_BASE_ADDRESS`
+    Core(u32),
+}
+
+impl InteractAddress {
+    pub const fn address(self) -> u32 {
+        match self {
+            InteractAddress::Index(index) => INTERACT_BASE_ADDRESS + index as u32 * 4,
+            InteractAddress::Core(address) => address,
+        }
+    }
+
+    fn is_valid(self) -> bool {
+        match self {
+            InteractAddress::Index(index) => index < INTERACT_COUNT,
+            InteractAddress::Core(address) => {
+                (CORE_BASE_ADDRESS..INTERACT_BASE_ADDRESS).contains(&address) && address & 0x3 == 0
+            }
+        }
+    }
+}
+
+/// One entry of a `list` variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListOption {
+    pub value: u32,
+    pub name: &'static str,
+}
+
+/// The type of an `interact.json` variable, and its type specific properties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractKind {
+    /// A button that writes `value` when pressed
+    Action {
+        value: u32,
+    },
+    /// A checkbox that writes `value` when checked, and 0 when unchecked
+    Check {
+        value: u32,
+        default: bool,
+    },
+    /// One option of a radio group. Every option in a group shares an address, and only one should be the default
+    Radio {
+        group: u32,
+        value: u32,
+        default: bool,
+    },
+    /// A dropdown of `options`
+    List {
+        default: u32,
+        options: &'static [ListOption],
+    },
+    SliderU32 {
+        default: u32,
+        min: u32,
+        max: u32,
+        adjust_small: u32,
+        adjust_large: u32,
+    },
+    NumberU32 {
+        default: u32,
+    },
+}
+
+impl InteractKind {
+    fn name(&self) -> &'static str {
+        match self {
+            InteractKind::Action { .. } => "action",
+            InteractKind::Check { .. } => "check",
+            InteractKind::Radio { .. } => "radio",
+            InteractKind::List { .. } => "list",
+            InteractKind::SliderU32 { .. } => "slider_u32",
+            InteractKind::NumberU32 { .. } => "number_u32",
+        }
+    }
+}
+
+/// A single `interact.json` variable, shown in the Pocket's `Core Settings` menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InteractVariable {
+    pub id: u32,
+    pub name: &'static str,
+    pub address: InteractAddress,
+    pub kind: InteractKind,
+    pub enabled: bool,
+    /// The Pocket remembers the value between launches
+    pub persist: bool,
+    /// The Pocket never reads the value back from the core
+    pub write_only: bool,
+}
+
+impl InteractVariable {
+    ///
+    /// An enabled variable, that isn't persisted and can be read back
+    ///
+    pub const fn new(
+        id: u32,
+        name: &'static str,
+        address: InteractAddress,
+        kind: InteractKind,
+    ) -> Self {
+        InteractVariable {
+            id,
+            name,
+            address,
+            kind,
+            enabled: true,
+            persist: false,
+            write_only: false,
+        }
+    }
+
+    pub const fn persist(mut self) -> Self {
+        self.persist = true;
+        self
+    }
+
+    pub const fn write_only(mut self) -> Self {
+        self.write_only = true;
+        self
+    }
+
+    pub const fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    ///
+    /// The `APF_INTERACT` index the variable is bound to, for binding `interact_settings!` fields to it. Panics if it
+    /// is bound to a core register, which fails the build when evaluated in a constant
+    ///
+    pub const fn index(&self) -> usize {
+        match self.address {
+            InteractAddress::Index(index) => index,
+            InteractAddress::Core(_) => panic!("Variable isn't bound to an APF_INTERACT index"),
+        }
+    }
+
+    ///
+    /// The current value of the variable, if it is bound to an `APF_INTERACT` index
+    ///
+    pub fn read(&self, interact: &Interact) -> Option<u32> {
+        match self.address {
+            InteractAddress::Index(index) if index < INTERACT_COUNT => Some(interact.get(index)),
+            _ => None,
+        }
+    }
+}
+
+/// A problem found while validating an `InteractDefinition`. Each holds the id of the offending variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractDefinitionError {
+    /// Two variables share an id
+    DuplicateId(u32),
+    /// The variable's address is outside of the core and `APF_INTERACT` ranges, or isn't word aligned
+    AddressOutOfRange(u32),
+    /// The variable shares an address with another variable that isn't in the same radio group
+    AddressConflict(u32),
+    /// The variable's default isn't one of its options, or is outside of its range
+    InvalidDefault(u32),
+}
+
+impl fmt::Display for InteractDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InteractDefinitionError::DuplicateId(id) => {
+                write!(f, "interact id {id} is used more than once")
+            }
+            InteractDefinitionError::AddressOutOfRange(id) => {
+                write!(
+                    f,
+                    "interact id {id} has an address outside of 0x1000_0000 to 0x1000_0140"
+                )
+            }
+            InteractDefinitionError::AddressConflict(id) => {
+                write!(
+                    f,
+                    "interact id {id} shares an address with another variable"
+                )
+            }
+            InteractDefinitionError::InvalidDefault(id) => {
+                write!(f, "interact id {id} has an invalid default")
+            }
+        }
+    }
+}
+
+/// The full set of `Core Settings` variables for a core, declared in Rust.
+///
+/// Declare a definition as a `const` in its own file, then `include!` that file from `build.rs` to generate
+/// `interact.json` with `to_json`, and use it as a module at runtime to look up and read variables, so the two can't
+/// drift apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InteractDefinition {
+    pub variables: &'static [InteractVariable],
+}
+
+impl InteractDefinition {
+    pub const fn new(variables: &'static [InteractVariable]) -> Self {
+        InteractDefinition { variables }
+    }
+
+    pub fn variable(&self, id: u32) -> Option<&InteractVariable> {
+        self.variables.iter().find(|variable| variable.id == id)
+    }
+
+    pub fn variable_named(&self, name: &str) -> Option<&InteractVariable> {
+        self.variables.iter().find(|variable| variable.name == name)
+    }
+
+    ///
+    /// Checks that ids are unique, addresses are in range and not shared, and defaults are valid
+    ///
+    pub fn validate(&self) -> Result<(), InteractDefinitionError> {
+        for (position, variable) in self.variables.iter().enumerate() {
+            let id = variable.id;
+
+            if !variable.address.is_valid() {
+                return Err(InteractDefinitionError::AddressOutOfRange(id));
+            }
+
+            let valid_default = match variable.kind {
+                InteractKind::List { default, options } => {
+                    options.iter().any(|option| option.value == default)
+                }
+                InteractKind::SliderU32 {
+                    default, min, max, ..
+                } => min <= max && (min..=max).contains(&default),
+                _ => true,
+            };
+
+            if !valid_default {
+                return Err(InteractDefinitionError::InvalidDefault(id));
+            }
+
+            for other in &self.variables[..position] {
+                if other.id == id {
+                    return Err(InteractDefinitionError::DuplicateId(id));
+                }
+
+                let same_group = match (variable.kind, other.kind) {
+                    (
+                        InteractKind::Radio { group, .. },
+                        InteractKind::Radio {
+                            group: other_group, ..
+                        },
+                    ) => group == other_group,
+                    _ => false,
+                };
+
+                if other.address.address() == variable.address.address() && !same_group {
+                    return Err(InteractDefinitionError::AddressConflict(id));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Validates the definition, and renders it as the `interact.json` the Pocket expects
+    ///
+    pub fn to_json(&self) -> Result<String, InteractDefinitionError> {
+        self.validate()?;
+
+        let mut json = String::new();
+
+        // Writing to a `String` can't fail
+        let _ = self.write_json(&mut json);
+
+        Ok(json)
+    }
+
+    ///
+    /// Validates the definition, and writes it to `interact.json` at `path`. Intended for `build.rs`. An existing file
+    /// with the same contents is left untouched, so builds don't modify the source tree
+    ///
+    #[cfg(feature = "std")]
+    pub fn write_json_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let json = self.to_json().map_err(|error| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, alloc::format!("{error}"))
+        })?;
+
+        if std::fs::read_to_string(&path).is_ok_and(|existing| existing == json) {
+            return Ok(());
+        }
+
+        std::fs::write(path, json)
+    }
+
+    fn write_json(&self, json: &mut String) -> fmt::Result {
+        json.push_str("{\n  \"interact\": {\n    \"magic\": \"APF_VER_1\",\n    \"variables\": [");
+
+        for (position, variable) in self.variables.iter().enumerate() {
+            if position > 0 {
+                json.push(',');
+            }
+
+            let mut fields = JsonObject::new(json, 6);
+
+            fields.string("name", variable.name)?;
+            fields.number("id", variable.id)?;
+            fields.string("type", variable.kind.name())?;
+
+            if let InteractKind::Radio { group, .. } = variable.kind {
+                fields.number("group", group)?;
+            }
+
+            fields.boolean("enabled", variable.enabled)?;
+            fields.hex("address", variable.address.address())?;
+
+            // Both default to false
+            if variable.persist {
+                fields.boolean("persist", true)?;
+            }
+
+            if variable.write_only {
+                fields.boolean("writeonly", true)?;
+            }
+
+            match variable.kind {
+                InteractKind::Action { value } => fields.number("value", value)?,
+                InteractKind::Check { value, default } => {
+                    fields.number("defaultval", default as u32)?;
+                    fields.number("value", value)?;
+                }
+                InteractKind::Radio { value, default, .. } => {
+                    if default {
+                        fields.number("defaultval", value)?;
+                    }
+
+                    fields.number("value", value)?;
+                }
+                InteractKind::List { default, options } => {
+                    fields.number("defaultval", default)?;
+                    fields.key("options")?;
+                    fields.json.push('[');
+
+                    for (position, option) in options.iter().enumerate() {
+                        if position > 0 {
+                            fields.json.push(',');
+                        }
+
+                        let mut option_fields = JsonObject::new(fields.json, 10);
+
+                        option_fields.number("value", option.value)?;
+                        option_fields.string("name", option.name)?;
+                        option_fields.close();
+                    }
+
+                    fields.json.push_str("\n        ]");
+                }
+                InteractKind::SliderU32 {
+                    default,
+                    min,
+                    max,
+                    adjust_small,
+                    adjust_large,
+                } => {
+                    fields.number("defaultval", default)?;
+                    fields.key("graphical")?;
+
+                    let mut graphical = JsonObject::inline(fields.json, 8);
+
+                    graphical.boolean("signed", false)?;
+                    graphical.number("min", min)?;
+                    graphical.number("max", max)?;
+                    graphical.number("adjust_small", adjust_small)?;
+                    graphical.number("adjust_large", adjust_large)?;
+                    graphical.close();
+                }
+                InteractKind::NumberU32 { default } => fields.number("defaultval", default)?,
+            }
+
+            fields.close();
+        }
+
+        json.push_str("\n    ],\n    \"messages\": []\n  }\n}\n");
+
+        Ok(())
+    }
+}
+
+// Writes the fields of one JSON object, indented by `indent` spaces
+struct JsonObject<'a> {
+    json: &'a mut String,
+    indent: usize,
+    empty: bool,
+}
+
+impl<'a> JsonObject<'a> {
+    // Opens an object as an array element, on its own line
+    fn new(json: &'a mut String, indent: usize) -> Self {
+        json.push('\n');
+        push_indent(json, indent);
+
+        JsonObject::inline(json, indent)
+    }
+
+    // Opens an object as a field value, after its key
+    fn inline(json: &'a mut String, indent: usize) -> Self {
+        json.push('{');
+
+        JsonObject {
+            json,
+            indent,
+            empty: true,
+        }
+    }
+
+    fn key(&mut self, key: &str) -> fmt::Result {
+        if !self.empty {
+            self.json.push(',');
+        }
+
+        self.empty = false;
+        self.json.push('\n');
+        push_indent(self.json, self.indent + 2);
+
+        write!(self.json, "\"{key}\": ")
+    }
+
+    fn string(&mut self, key: &str, value: &str) -> fmt::Result {
+        self.key(key)?;
+        self.json.push('"');
+
+        for character in value.chars() {
+            match character {
+                '"' => self.json.push_str("\\\""),
+                '\\' => self.json.push_str("\\\\"),
+                character if (character as u32) < 0x20 => {
+                    write!(self.json, "\\u{:04x}", character as u32)?
+                }
+                character => self.json.push(character),
+            }
+        }
+
+        self.json.push('"');
+
+        Ok(())
+    }
+
+    fn number(&mut self, key: &str, value: u32) -> fmt::Result {
+        self.key(key)?;
+
+        write!(self.json, "{value}")
+    }
+
+    fn hex(&mut self, key: &str, value: u32) -> fmt::Result {
+        self.key(key)?;
+
+        write!(self.json, "\"0x{value:08X}\"")
+    }
+
+    fn boolean(&mut self, key: &str, value: bool) -> fmt::Result {
+        self.key(key)?;
+
+        write!(self.json, "{value}")
+    }
+
+    fn close(self) {
+        self.json.push('\n');
+        push_indent(self.json, self.indent);
+        self.json.push('}');
+    }
+}
+
+fn push_indent(json: &mut String, indent: usize) {
+    for _ in 0..indent {
+        json.push(' ');
+    }
+}
@@ -1,16 +1,75 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 // Export crates
+#[cfg(feature = "embedded-graphics")]
+pub use embedded_graphics_core;
 pub use litex_pac;
+pub use rand_core;
 
+pub mod analog;
+pub mod audio;
+pub mod bridge;
+pub mod ctrl;
+pub mod damage;
+pub mod device;
+#[cfg(feature = "embedded-graphics")]
+pub mod draw_target;
 pub mod file;
+pub mod frame_clock;
+pub mod graphics;
+pub mod input;
+pub mod input_state;
+pub mod interact;
+pub mod interact_definition;
+pub mod mixer;
+#[cfg(feature = "std")]
+pub mod mock_bridge;
+pub mod pocket;
+pub mod replay;
+pub mod rng;
+pub mod rtc;
+pub mod save;
+pub mod scene;
 #[cfg(feature = "slint")]
 pub mod slint_platform;
+pub mod slot_reader;
+pub mod stream;
+pub mod timer;
 pub mod uart_printer;
+pub mod video;
+pub mod wav;
 
+pub use analog::*;
+pub use audio::*;
+pub use bridge::*;
+pub use ctrl::*;
+pub use damage::*;
+pub use device::*;
 pub use file::*;
+pub use frame_clock::*;
+pub use graphics::*;
+pub use input::*;
+pub use input_state::*;
+pub use interact::*;
+pub use interact_definition::*;
+pub use mixer::*;
+pub use pocket::*;
+pub use replay::*;
+pub use rng::*;
+pub use rtc::*;
+pub use save::*;
+pub use scene::*;
+pub use slot_reader::*;
+pub use stream::*;
+pub use timer::*;
 pub use uart_printer::*;
+pub use video::*;
+pub use wav::*;
 
+#[cfg(feature = "std")]
+pub use mock_bridge::*;
 #[cfg(feature = "slint")]
 pub use slint_platform::*;
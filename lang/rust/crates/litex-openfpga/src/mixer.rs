@@ -0,0 +1,544 @@
+use crate::{Audio, StereoSample};
+
+/// The rate the audio FIFO is played back at
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// The default number of samples `Mixer::fill` keeps queued. A frame at 60Hz, plus a safety margin
+pub const DEFAULT_TARGET_FILL: usize = SAMPLE_RATE as usize / 60 + 200;
+
+// Envelope level of 1.0, in 16.16 fixed point
+const UNITY: u32 = 1 << 16;
+
+/// The shape of an oscillator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Saw,
+    Triangle,
+    /// White noise, resampled at the oscillator frequency
+    Noise,
+}
+
+/// A linear ADSR envelope. Times are in samples at `SAMPLE_RATE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    pub attack: u32,
+    pub decay: u32,
+    /// The level held after decay, until the voice is released. `u16::MAX` is full volume
+    pub sustain: u16,
+    pub release: u32,
+}
+
+impl Envelope {
+    /// Full volume from the first sample until release
+    pub const NONE: Envelope = Envelope::new(0, 0, u16::MAX, 0);
+
+    pub const fn new(attack: u32, decay: u32, sustain: u16, release: u32) -> Self {
+        Envelope {
+            attack,
+            decay,
+            sustain,
+            release,
+        }
+    }
+
+    fn sustain_level(&self) -> u32 {
+        // Map `u16::MAX` to exactly `UNITY`
+        self.sustain as u32 + (self.sustain as u32 >> 15)
+    }
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope::NONE
+    }
+}
+
+/// Mono 16 bit PCM data in RAM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pcm<'a> {
+    pub samples: &'a [i16],
+    pub sample_rate: u32,
+    /// Where playback restarts on reaching the end. `None` plays once
+    pub loop_start: Option<usize>,
+}
+
+impl<'a> Pcm<'a> {
+    pub const fn new(samples: &'a [i16], sample_rate: u32) -> Self {
+        Pcm {
+            samples,
+            sample_rate,
+            loop_start: None,
+        }
+    }
+
+    pub const fn looping(self, loop_start: usize) -> Self {
+        Pcm {
+            loop_start: Some(loop_start),
+            ..self
+        }
+    }
+}
+
+/// Where a voice's samples come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source<'a> {
+    Pcm(Pcm<'a>),
+    Oscillator { waveform: Waveform, frequency: u32 },
+}
+
+/// A sound to start on a `Mixer` voice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sound<'a> {
+    pub source: Source<'a>,
+    pub envelope: Envelope,
+    /// 255 is full volume
+    pub volume: u8,
+    /// -128 is fully left, 0 is centered, and 127 is fully right
+    pub pan: i8,
+    /// The number of samples before the voice is automatically released. `None` holds until `Mixer::release`
+    pub duration: Option<u32>,
+}
+
+impl<'a> Sound<'a> {
+    pub const fn pcm(pcm: Pcm<'a>) -> Self {
+        Sound::new(Source::Pcm(pcm))
+    }
+
+    ///
+    /// An oscillator at `frequency` Hz
+    ///
+    pub const fn tone(waveform: Waveform, frequency: u32) -> Self {
+        Sound::new(Source::Oscillator {
+            waveform,
+            frequency,
+        })
+    }
+
+    const fn new(source: Source<'a>) -> Self {
+        Sound {
+            source,
+            envelope: Envelope::NONE,
+            volume: u8::MAX,
+            pan: 0,
+            duration: None,
+        }
+    }
+
+    pub const fn with_envelope(self, envelope: Envelope) -> Self {
+        Sound { envelope, ..self }
+    }
+
+    pub const fn with_volume(self, volume: u8) -> Self {
+        Sound { volume, ..self }
+    }
+
+    pub const fn with_pan(self, pan: i8) -> Self {
+        Sound { pan, ..self }
+    }
+
+    pub const fn with_duration(self, samples: u32) -> Self {
+        Sound {
+            duration: Some(samples),
+            ..self
+        }
+    }
+}
+
+/// A handle to a `Mixer` voice, returned when a sound is started.
+///
+/// Handles aren't invalidated when a sound ends, so a stale handle controls whatever sound is later started on the
+/// same voice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoiceId(usize);
+
+impl VoiceId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Clone, Copy)]
+struct Voice<'a> {
+    // `None` when the voice is idle
+    sound: Option<Sound<'a>>,
+    stage: Stage,
+    stage_position: u32,
+    elapsed: u32,
+    // Current envelope level, and the level release started from
+    level: u32,
+    release_level: u32,
+    // Oscillators use the low 32 bits as a full cycle. PCM uses 32.32 fixed point sample indices
+    phase: u64,
+    step: u64,
+    noise_state: u32,
+    noise_value: i16,
+}
+
+impl<'a> Voice<'a> {
+    const IDLE: Voice<'static> = Voice {
+        sound: None,
+        stage: Stage::Attack,
+        stage_position: 0,
+        elapsed: 0,
+        level: 0,
+        release_level: 0,
+        phase: 0,
+        step: 0,
+        noise_state: 0x1234_5679,
+        noise_value: 0,
+    };
+
+    fn start(&mut self, sound: Sound<'a>) {
+        *self = Voice {
+            sound: Some(sound),
+            noise_state: self.noise_state,
+            ..Voice::IDLE
+        };
+
+        self.update_step();
+    }
+
+    fn update_step(&mut self) {
+        let rate = match self.sound.map(|sound| sound.source) {
+            Some(Source::Pcm(pcm)) => pcm.sample_rate,
+            Some(Source::Oscillator { frequency, .. }) => frequency,
+            None => 0,
+        };
+
+        // Rounded, so frequencies that divide the sample rate stay in step with it
+        self.step = (((rate as u64) << 32) + SAMPLE_RATE as u64 / 2) / SAMPLE_RATE as u64;
+    }
+
+    fn release(&mut self) {
+        if self.sound.is_some() && self.stage != Stage::Release {
+            self.release_level = self.level;
+            self.stage = Stage::Release;
+            self.stage_position = 0;
+        }
+    }
+
+    ///
+    /// Produces the next mono sample, with the envelope and volume applied
+    ///
+    fn next_sample(&mut self) -> i32 {
+        let Some(sound) = self.sound else {
+            return 0;
+        };
+
+        let raw = match sound.source {
+            Source::Pcm(pcm) => match self.next_pcm(pcm) {
+                Some(sample) => sample,
+                None => {
+                    self.sound = None;
+
+                    return 0;
+                }
+            },
+            Source::Oscillator { waveform, .. } => self.next_oscillator(waveform),
+        };
+
+        if sound
+            .duration
+            .is_some_and(|duration| self.elapsed >= duration)
+        {
+            self.release();
+        }
+
+        self.elapsed = self.elapsed.saturating_add(1);
+
+        let level = self.advance_envelope(sound.envelope);
+        // `level` is at most `UNITY`, so this can't overflow
+        let sample = (raw * (level >> 1) as i32) >> 15;
+        let gain = sound.volume as i32 + (sound.volume as i32 >> 7);
+
+        (sample * gain) >> 8
+    }
+
+    fn next_pcm(&mut self, pcm: Pcm) -> Option<i32> {
+        let length = pcm.samples.len() as u64;
+        let mut index = self.phase >> 32;
+
+        if index >= length {
+            let loop_start = pcm.loop_start.filter(|start| (*start as u64) < length)? as u64;
+
+            index = loop_start + (index - loop_start) % (length - loop_start);
+            self.phase = (index << 32) | (self.phase & 0xFFFF_FFFF);
+        }
+
+        self.phase += self.step;
+
+        Some(pcm.samples[index as usize] as i32)
+    }
+
+    fn next_oscillator(&mut self, waveform: Waveform) -> i32 {
+        let phase = self.phase as u32;
+        let next = phase.wrapping_add(self.step as u32);
+
+        self.phase = next as u64;
+
+        match waveform {
+            Waveform::Square => {
+                if phase < 0x8000_0000 {
+                    i16::MAX as i32
+                } else {
+                    -(i16::MAX as i32)
+                }
+            }
+            Waveform::Saw => (phase >> 16) as i32 - 0x8000,
+            Waveform::Triangle => {
+                let position = (phase >> 15) as i32;
+
+                if position < 0x1_0000 {
+                    position - 0x8000
+                } else {
+                    0x1_8000 - position - 1
+                }
+            }
+            Waveform::Noise => {
+                // New value every cycle
+                if next < phase || self.noise_value == 0 {
+                    // xorshift32
+                    let mut state = self.noise_state;
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+
+                    self.noise_state = state;
+                    self.noise_value = (state >> 16) as i16 | 1;
+                }
+
+                self.noise_value as i32
+            }
+        }
+    }
+
+    fn advance_envelope(&mut self, envelope: Envelope) -> u32 {
+        let level = loop {
+            let position = self.stage_position;
+
+            match self.stage {
+                Stage::Attack if position < envelope.attack => {
+                    break ramp(0, UNITY, position, envelope.attack)
+                }
+                Stage::Decay if position < envelope.decay => {
+                    break ramp(UNITY, envelope.sustain_level(), position, envelope.decay)
+                }
+                Stage::Release if position < envelope.release => {
+                    break ramp(self.release_level, 0, position, envelope.release)
+                }
+                Stage::Attack => {
+                    self.stage = Stage::Decay;
+                    self.stage_position = 0;
+                }
+                Stage::Decay => {
+                    self.stage = Stage::Sustain;
+                    self.stage_position = 0;
+                }
+                Stage::Sustain if envelope.sustain_level() == 0 => {
+                    // Nothing more can be heard
+                    self.sound = None;
+                    break 0;
+                }
+                Stage::Sustain => break envelope.sustain_level(),
+                Stage::Release => {
+                    self.sound = None;
+                    break 0;
+                }
+            }
+        };
+
+        self.stage_position = self.stage_position.saturating_add(1);
+        self.level = level;
+
+        level
+    }
+}
+
+fn ramp(from: u32, to: u32, position: u32, length: u32) -> u32 {
+    let delta = to as i64 - from as i64;
+
+    (from as i64 + delta * position as i64 / length as i64) as u32
+}
+
+/// A fixed point software mixer, playing up to `N` sounds at once.
+///
+/// Call `fill` each frame to render just enough audio to keep `target_fill` samples queued in the FIFO. `render`
+/// mixes into any buffer, so the mixer can also be driven off device.
+pub struct Mixer<'a, const N: usize> {
+    voices: [Voice<'a>; N],
+    master_volume: u8,
+    target_fill: usize,
+}
+
+impl<'a, const N: usize> Mixer<'a, N> {
+    pub fn new() -> Self {
+        Mixer {
+            voices: [Voice::IDLE; N],
+            master_volume: u8::MAX,
+            target_fill: DEFAULT_TARGET_FILL,
+        }
+    }
+
+    ///
+    /// Starts `sound` on the first idle voice. Returns `None` if every voice is busy
+    ///
+    pub fn play(&mut self, sound: Sound<'a>) -> Option<VoiceId> {
+        let index = self.voices.iter().position(|voice| voice.sound.is_none())?;
+
+        self.voices[index].start(sound);
+
+        Some(VoiceId(index))
+    }
+
+    ///
+    /// Starts `sound` on `voice`, replacing anything already playing there
+    ///
+    pub fn play_on(&mut self, voice: VoiceId, sound: Sound<'a>) {
+        self.voices[voice.0].start(sound);
+    }
+
+    ///
+    /// Moves `voice` into the release stage of its envelope
+    ///
+    pub fn release(&mut self, voice: VoiceId) {
+        self.voices[voice.0].release();
+    }
+
+    ///
+    /// Silences `voice` immediately
+    ///
+    pub fn stop(&mut self, voice: VoiceId) {
+        self.voices[voice.0].sound = None;
+    }
+
+    pub fn stop_all(&mut self) {
+        for voice in self.voices.iter_mut() {
+            voice.sound = None;
+        }
+    }
+
+    pub fn is_playing(&self, voice: VoiceId) -> bool {
+        self.voices[voice.0].sound.is_some()
+    }
+
+    ///
+    /// The number of voices currently playing
+    ///
+    pub fn active_voices(&self) -> usize {
+        self.voices
+            .iter()
+            .filter(|voice| voice.sound.is_some())
+            .count()
+    }
+
+    pub fn set_volume(&mut self, voice: VoiceId, volume: u8) {
+        if let Some(sound) = self.voices[voice.0].sound.as_mut() {
+            sound.volume = volume;
+        }
+    }
+
+    pub fn set_pan(&mut self, voice: VoiceId, pan: i8) {
+        if let Some(sound) = self.voices[voice.0].sound.as_mut() {
+            sound.pan = pan;
+        }
+    }
+
+    ///
+    /// Changes the frequency of an oscillator, or the sample rate of PCM data, without restarting the voice
+    ///
+    pub fn set_frequency(&mut self, voice: VoiceId, frequency: u32) {
+        let voice = &mut self.voices[voice.0];
+
+        match voice.sound.as_mut().map(|sound| &mut sound.source) {
+            Some(Source::Pcm(pcm)) => pcm.sample_rate = frequency,
+            Some(Source::Oscillator {
+                frequency: current, ..
+            }) => *current = frequency,
+            None => return,
+        }
+
+        voice.update_step();
+    }
+
+    pub fn master_volume(&self) -> u8 {
+        self.master_volume
+    }
+
+    pub fn set_master_volume(&mut self, volume: u8) {
+        self.master_volume = volume;
+    }
+
+    pub fn target_fill(&self) -> usize {
+        self.target_fill
+    }
+
+    ///
+    /// Sets the number of samples `fill` keeps queued, up to one less than the FIFO capacity. Larger values survive
+    /// longer frames, at the cost of latency
+    ///
+    pub fn set_target_fill(&mut self, samples: usize) {
+        self.target_fill = samples;
+    }
+
+    ///
+    /// Mixes the next `output.len()` samples into `output`
+    ///
+    pub fn render(&mut self, output: &mut [StereoSample]) {
+        let master = self.master_volume as i32 + (self.master_volume as i32 >> 7);
+
+        for sample in output.iter_mut() {
+            let mut left = 0i32;
+            let mut right = 0i32;
+
+            for voice in self.voices.iter_mut() {
+                let Some(sound) = voice.sound else {
+                    continue;
+                };
+
+                let value = voice.next_sample();
+                // Balance, so centered sounds play at full volume on both channels
+                let pan = sound.pan as i32;
+                let left_gain = 128 - pan.max(0);
+                let right_gain = 128 + pan.min(0);
+
+                left += (value * left_gain) >> 7;
+                right += (value * right_gain) >> 7;
+            }
+
+            *sample = StereoSample::new(
+                clamp_sample((left * master) >> 8),
+                clamp_sample((right * master) >> 8),
+            );
+        }
+    }
+
+    ///
+    /// Renders enough audio to bring the FIFO up to `target_fill`, returning the number of samples pushed
+    ///
+    pub fn fill(&mut self, audio: &mut Audio) -> usize {
+        audio.fill_to(self.target_fill, |chunk| {
+            self.render(chunk);
+
+            chunk.len()
+        })
+    }
+}
+
+impl<'a, const N: usize> Default for Mixer<'a, N> {
+    fn default() -> Self {
+        Mixer::new()
+    }
+}
+
+fn clamp_sample(value: i32) -> i16 {
+    value.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
@@ -0,0 +1,415 @@
+use core::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::{format, vec};
+
+use crate::{BridgeBackend, BridgeRequest, MAX_PATH_LENGTH};
+
+// Result codes, as decoded by `BridgeError::from_result_code`
+const RESULT_OK: u8 = 0;
+const RESULT_SLOT_NOT_FOUND: u8 = 1;
+const RESULT_OUT_OF_RANGE: u8 = 2;
+const RESULT_FILE_ERROR: u8 = 3;
+
+/// A failure injected into a `MockBridge` with `MockBridge::fail_next`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockFault {
+    /// The result code the request completes with
+    pub result_code: u8,
+    /// The number of bytes transferred before the request fails
+    pub after_bytes: u32,
+}
+
+/// A host `BridgeBackend` backed by a directory of files, for testing file code off device.
+///
+/// Slot `n` is the file `n.bin` in the root directory, until an openfile request switches it to another path. RAM
+/// addresses are host pointers, and like the real bridge, reads into RAM are performed in whole words.
+///
+/// Transfers can be slowed down with `with_latency` and `with_chunk_size`, so they complete over several calls to
+/// `check_op_complete`, and can be made to fail with `fail_next`.
+pub struct MockBridge {
+    state: RefCell<MockState>,
+}
+
+struct MockState {
+    root: PathBuf,
+    // Slots switched to a different file by openfile
+    paths: BTreeMap<u32, PathBuf>,
+    latency: u32,
+    chunk_size: u32,
+    faults: VecDeque<MockFault>,
+    pending: Option<PendingTransfer>,
+    complete: bool,
+    result_code: u8,
+    current_address: u32,
+}
+
+struct PendingTransfer {
+    request: BridgeRequest,
+    data_offset: u32,
+    length: u32,
+    storage_address: usize,
+    bridge_slot_id: u32,
+    // Polls left before any data moves
+    wait: u32,
+    transferred: u32,
+    fault: Option<MockFault>,
+}
+
+impl MockBridge {
+    ///
+    /// Creates a bridge whose slots are files in `root`. Requests complete on the first poll
+    ///
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        MockBridge {
+            state: RefCell::new(MockState {
+                root: root.into(),
+                paths: BTreeMap::new(),
+                latency: 0,
+                chunk_size: u32::MAX,
+                faults: VecDeque::new(),
+                pending: None,
+                complete: false,
+                result_code: RESULT_OK,
+                current_address: 0,
+            }),
+        }
+    }
+
+    ///
+    /// Makes every request report busy for `polls` calls to `check_op_complete` before transferring anything
+    ///
+    pub fn with_latency(self, polls: u32) -> Self {
+        self.state.borrow_mut().latency = polls;
+        self
+    }
+
+    ///
+    /// Limits transfers to `bytes` per call to `check_op_complete`, rounded up to a whole word
+    ///
+    pub fn with_chunk_size(self, bytes: u32) -> Self {
+        self.state.borrow_mut().chunk_size = ((bytes + 3) & !0x3).max(4);
+        self
+    }
+
+    ///
+    /// Makes the next request fail with `result_code` after transferring `after_bytes`. Faults queue up, one per request
+    ///
+    pub fn fail_next(&self, result_code: u8, after_bytes: u32) {
+        self.state.borrow_mut().faults.push_back(MockFault {
+            result_code,
+            after_bytes,
+        });
+    }
+
+    ///
+    /// The host file currently backing slot `bridge_slot_id`
+    ///
+    pub fn slot_path(&self, bridge_slot_id: u32) -> PathBuf {
+        self.state.borrow().slot_path(bridge_slot_id)
+    }
+
+    ///
+    /// True while a request has been started and hasn't completed yet
+    ///
+    pub fn is_busy(&self) -> bool {
+        self.state.borrow().pending.is_some()
+    }
+}
+
+impl MockState {
+    fn slot_path(&self, bridge_slot_id: u32) -> PathBuf {
+        match self.paths.get(&bridge_slot_id) {
+            Some(path) => path.clone(),
+            None => self.root.join(format!("{bridge_slot_id}.bin")),
+        }
+    }
+
+    fn file_size(&self, bridge_slot_id: u32) -> Option<u32> {
+        fs::metadata(self.slot_path(bridge_slot_id))
+            .ok()
+            .map(|metadata| metadata.len() as u32)
+    }
+
+    ///
+    /// Moves the pending transfer along by one poll, returning its result code once it is finished
+    ///
+    fn step(&mut self) -> Option<u8> {
+        let chunk_size = self.chunk_size;
+        let mut pending = self.pending.take()?;
+
+        if pending.wait > 0 {
+            pending.wait -= 1;
+            self.pending = Some(pending);
+
+            return None;
+        }
+
+        let (limit, fault_code) = match pending.fault {
+            Some(fault) => (
+                fault.after_bytes.min(pending.length),
+                Some(fault.result_code),
+            ),
+            None => (pending.length, None),
+        };
+
+        let count = (limit - pending.transferred.min(limit)).min(chunk_size);
+
+        if count > 0 {
+            if let Err(code) = self.transfer(&pending, count) {
+                return Some(code);
+            }
+
+            pending.transferred += count;
+            self.current_address =
+                (pending.storage_address as u32).wrapping_add(pending.transferred);
+        }
+
+        if pending.transferred < limit {
+            self.pending = Some(pending);
+
+            return None;
+        }
+
+        match fault_code {
+            Some(code) => Some(code),
+            None => Some(self.finish(&pending)),
+        }
+    }
+
+    ///
+    /// Moves the next `count` bytes of a read or write between RAM and the slot's file
+    ///
+    fn transfer(&self, pending: &PendingTransfer, count: u32) -> Result<(), u8> {
+        let offset = pending.data_offset as u64 + pending.transferred as u64;
+        let address = pending.storage_address + pending.transferred as usize;
+        let path = self.slot_path(pending.bridge_slot_id);
+
+        match pending.request {
+            BridgeRequest::Read => {
+                // The bridge writes whole words into RAM, zero padding past the requested length
+                let mut bytes = vec![0u8; count.div_ceil(4) as usize * 4];
+
+                let mut file = fs::File::open(&path).map_err(|_| RESULT_SLOT_NOT_FOUND)?;
+                file.seek(SeekFrom::Start(offset))
+                    .and_then(|_| file.read_exact(&mut bytes[..count as usize]))
+                    .map_err(|_| RESULT_FILE_ERROR)?;
+
+                // Safety: this mirrors the bridge's DMA. `File` only issues reads into RAM it has borrowed
+                unsafe {
+                    core::ptr::copy_nonoverlapping(bytes.as_ptr(), address as *mut u8, bytes.len());
+                }
+            }
+            BridgeRequest::Write => {
+                let mut bytes = vec![0u8; count as usize];
+
+                // Safety: this mirrors the bridge's DMA. `File` only issues writes from RAM it has borrowed
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        address as *const u8,
+                        bytes.as_mut_ptr(),
+                        bytes.len(),
+                    );
+                }
+
+                let mut file = OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .map_err(|_| RESULT_SLOT_NOT_FOUND)?;
+                file.seek(SeekFrom::Start(offset))
+                    .and_then(|_| file.write_all(&bytes))
+                    .map_err(|_| RESULT_FILE_ERROR)?;
+            }
+            BridgeRequest::GetFile | BridgeRequest::OpenFile => {}
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Completes a request once all of its data has moved, returning the result code
+    ///
+    fn finish(&mut self, pending: &PendingTransfer) -> u8 {
+        match pending.request {
+            BridgeRequest::Read | BridgeRequest::Write => RESULT_OK,
+            BridgeRequest::GetFile => self.get_file(pending),
+            BridgeRequest::OpenFile => self.open_file(pending),
+        }
+    }
+
+    fn get_file(&self, pending: &PendingTransfer) -> u8 {
+        let path = self.slot_path(pending.bridge_slot_id);
+
+        if !path.exists() {
+            return RESULT_SLOT_NOT_FOUND;
+        }
+
+        // Report paths relative to the root, like the Pocket reports paths relative to the SD card
+        let path = path.strip_prefix(&self.root).unwrap_or(&path);
+        let path = path.to_string_lossy();
+
+        let mut bytes = [0u8; MAX_PATH_LENGTH];
+        let length = path.len().min(MAX_PATH_LENGTH - 1);
+        bytes[..length].copy_from_slice(&path.as_bytes()[..length]);
+
+        // Safety: the response struct is a buffer of `MAX_PATH_LENGTH` bytes owned by `File::get_path`
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                pending.storage_address as *mut u8,
+                bytes.len(),
+            );
+        }
+
+        RESULT_OK
+    }
+
+    fn open_file(&mut self, pending: &PendingTransfer) -> u8 {
+        // Path, flags, and size
+        let mut bytes = [0u8; MAX_PATH_LENGTH + 8];
+
+        // Safety: the parameter struct is a buffer of this length owned by `File::open_path_with`
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                pending.storage_address as *const u8,
+                bytes.as_mut_ptr(),
+                bytes.len(),
+            );
+        }
+
+        let length = bytes[..MAX_PATH_LENGTH]
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(MAX_PATH_LENGTH);
+        let Ok(path) = core::str::from_utf8(&bytes[..length]) else {
+            return RESULT_FILE_ERROR;
+        };
+
+        // Bridge structs are big endian
+        let word = |index: usize| {
+            u32::from_be_bytes([
+                bytes[index],
+                bytes[index + 1],
+                bytes[index + 2],
+                bytes[index + 3],
+            ])
+        };
+        let flags = word(MAX_PATH_LENGTH);
+        let size = word(MAX_PATH_LENGTH + 4);
+
+        let path = self.root.join(path);
+
+        match open_host_file(&path, flags & 0x1 != 0, (flags & 0x2 != 0).then_some(size)) {
+            Ok(()) => {
+                self.paths.insert(pending.bridge_slot_id, path);
+
+                RESULT_OK
+            }
+            Err(_) => RESULT_FILE_ERROR,
+        }
+    }
+}
+
+fn open_host_file(path: &Path, create: bool, size: Option<u32>) -> io::Result<()> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(create)
+        .truncate(false)
+        .open(path)?;
+
+    if let Some(size) = size {
+        file.set_len(size as u64)?;
+    }
+
+    Ok(())
+}
+
+impl BridgeBackend for MockBridge {
+    fn can_access(&self, _address: usize, _length: usize) -> bool {
+        // All host memory is reachable
+        true
+    }
+
+    fn start_request(
+        &self,
+        request: BridgeRequest,
+        data_offset: u32,
+        length: u32,
+        storage_address: usize,
+        bridge_slot_id: u32,
+    ) {
+        let mut state = self.state.borrow_mut();
+
+        let fault = state.faults.pop_front();
+
+        state.complete = false;
+        state.current_address = storage_address as u32;
+        state.pending = Some(PendingTransfer {
+            request,
+            data_offset,
+            length,
+            storage_address,
+            bridge_slot_id,
+            wait: state.latency,
+            transferred: 0,
+            fault,
+        });
+
+        // Transfers that can never succeed fail up front, as the Pocket checks them before moving any data
+        if matches!(request, BridgeRequest::Read | BridgeRequest::Write) && fault.is_none() {
+            let code = match state.file_size(bridge_slot_id) {
+                None => Some(RESULT_SLOT_NOT_FOUND),
+                // Neither reads nor writes may extend past the end of the file. Writers grow it with `set_size` first
+                Some(size) if data_offset as u64 + length as u64 > size as u64 => {
+                    Some(RESULT_OUT_OF_RANGE)
+                }
+                Some(_) => None,
+            };
+
+            if let Some(code) = code {
+                if let Some(pending) = state.pending.as_mut() {
+                    pending.fault = Some(MockFault {
+                        result_code: code,
+                        after_bytes: 0,
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_op_complete(&self) -> bool {
+        let mut state = self.state.borrow_mut();
+
+        if let Some(code) = state.step() {
+            state.result_code = code;
+            state.complete = true;
+        }
+
+        // Cleared on read
+        core::mem::take(&mut state.complete)
+    }
+
+    fn command_result_code(&self) -> u8 {
+        self.state.borrow().result_code
+    }
+
+    fn current_address(&self) -> u32 {
+        self.state.borrow().current_address
+    }
+
+    fn file_size(&self, bridge_slot_id: u32) -> u32 {
+        self.state.borrow().file_size(bridge_slot_id).unwrap_or(0)
+    }
+
+    fn set_file_size(&self, bridge_slot_id: u32, size: u32) {
+        let path = self.state.borrow().slot_path(bridge_slot_id);
+
+        // Slots without a file have no size to change
+        if let Ok(file) = OpenOptions::new().write(true).open(path) {
+            let _ = file.set_len(size as u64);
+        }
+    }
+}
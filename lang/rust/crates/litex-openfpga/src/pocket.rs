@@ -0,0 +1,61 @@
+use litex_pac as pac;
+use pac::constants;
+
+use crate::{Audio, Ctrl, Device, File, Input, Interact, Rtc, Timer, Video, UART};
+
+/// The Pocket's peripherals, split into their owned drivers.
+///
+/// Only a single `Pocket` can be taken, so each block of registers has exactly one owner. Individual
+/// drivers can be moved out of this struct and handed to whatever part of the program needs them.
+pub struct Pocket {
+    pub audio: Audio,
+    pub bridge: File,
+    pub ctrl: Ctrl,
+    pub device: Device,
+    pub input: Input,
+    pub interact: Interact,
+    pub rtc: Rtc,
+    pub timer: Timer,
+    pub uart: UART,
+    pub video: Video,
+}
+
+impl Pocket {
+    ///
+    /// Returns the Pocket's drivers *once*. All subsequent calls will return `None`
+    ///
+    pub fn take() -> Option<Self> {
+        pac::Peripherals::take().map(Pocket::from_peripherals)
+    }
+
+    ///
+    /// Unchecked version of `Pocket::take`
+    ///
+    /// # Safety
+    ///
+    /// Each of the returned drivers must be the only user of its registers. This is intended for
+    /// places like the panic handler, where the original `Pocket` can't be reached
+    ///
+    pub unsafe fn steal() -> Self {
+        Pocket::from_peripherals(pac::Peripherals::steal())
+    }
+
+    pub fn from_peripherals(peripherals: pac::Peripherals) -> Self {
+        Pocket {
+            audio: Audio::new(peripherals.APF_AUDIO),
+            bridge: File::new(peripherals.APF_BRIDGE),
+            ctrl: Ctrl::new(peripherals.CTRL),
+            device: Device::new(peripherals.APF_ID),
+            input: Input::new(peripherals.APF_INPUT),
+            interact: Interact::new(peripherals.APF_INTERACT),
+            rtc: Rtc::new(peripherals.APF_RTC),
+            timer: Timer::new(peripherals.TIMER0, constants::CONFIG_CLOCK_FREQUENCY),
+            uart: UART::new(peripherals.UART),
+            video: Video::new(
+                peripherals.APF_VIDEO,
+                peripherals.VIDEO_FRAMEBUFFER,
+                peripherals.VIDEO_FRAMEBUFFER_VTG,
+            ),
+        }
+    }
+}
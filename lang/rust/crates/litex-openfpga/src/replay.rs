@@ -0,0 +1,426 @@
+use alloc::vec::Vec;
+
+use crate::{
+    frames_since, BridgeBackend, BridgeError, File, InputSnapshot, InputSource, CONTROLLER_COUNT,
+    FRAME_COUNTER_MASK,
+};
+
+// "LXIR"
+const REPLAY_MAGIC: u32 = 0x5249_584C;
+const REPLAY_VERSION: u32 = 1;
+
+// Magic, version, seed, and frame count words
+const HEADER_LENGTH: usize = 4 * 4;
+
+// `key`, `joy`, and `trig` for every controller
+const REGISTER_COUNT: usize = 3 * CONTROLLER_COUNT;
+
+/// A failed replay load.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The data isn't an input recording
+    NotReplay,
+    /// The recording was made by an incompatible version of this crate
+    UnsupportedVersion,
+    /// The recording ends early
+    Truncated,
+}
+
+fn to_registers(snapshot: &InputSnapshot) -> [u32; REGISTER_COUNT] {
+    let mut registers = [0; REGISTER_COUNT];
+
+    registers[..CONTROLLER_COUNT].copy_from_slice(&snapshot.key);
+    registers[CONTROLLER_COUNT..2 * CONTROLLER_COUNT].copy_from_slice(&snapshot.joy);
+    registers[2 * CONTROLLER_COUNT..].copy_from_slice(&snapshot.trig);
+
+    registers
+}
+
+fn from_registers(registers: &[u32; REGISTER_COUNT]) -> InputSnapshot {
+    let mut snapshot = InputSnapshot::default();
+
+    snapshot.key.copy_from_slice(&registers[..CONTROLLER_COUNT]);
+    snapshot
+        .joy
+        .copy_from_slice(&registers[CONTROLLER_COUNT..2 * CONTROLLER_COUNT]);
+    snapshot
+        .trig
+        .copy_from_slice(&registers[2 * CONTROLLER_COUNT..]);
+
+    snapshot
+}
+
+/// Records a session's input, one `InputSnapshot` per frame, for later deterministic replay by `InputPlayback`.
+///
+/// Identical consecutive frames are run length encoded, and each run only stores the registers that changed, so idle
+/// stretches cost almost nothing. A recording also holds a seed, such as `Rtc::unix_seconds` at startup, which the
+/// application should derive all of its randomness from.
+pub struct InputRecorder {
+    seed: u32,
+    frames: u32,
+    data: Vec<u8>,
+    // The registers as of the last encoded run
+    encoded: [u32; REGISTER_COUNT],
+    // The run being accumulated: its snapshot, frames elapsed per entry, and length
+    run: Option<(InputSnapshot, u32, u32)>,
+    last_frame: Option<u32>,
+}
+
+impl InputRecorder {
+    pub fn new(seed: u32) -> Self {
+        InputRecorder {
+            seed,
+            frames: 0,
+            data: Vec::new(),
+            encoded: [0; REGISTER_COUNT],
+            run: None,
+            last_frame: None,
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    ///
+    /// The number of frames recorded
+    ///
+    pub fn len(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    ///
+    /// Records the state of `input` for this frame, given the current `VideoStatus::frame_counter`. Like
+    /// `InputState::update`, repeated calls within a single frame are ignored
+    ///
+    pub fn record<S: InputSource + ?Sized>(&mut self, input: &S, frame_counter: u32) {
+        let frames = frames_since(self.last_frame, frame_counter);
+
+        if frames == 0 {
+            return;
+        }
+
+        self.last_frame = Some(frame_counter);
+        self.record_snapshot(input.snapshot(), frames);
+    }
+
+    ///
+    /// Records `snapshot` as lasting for `frames` displayed frames
+    ///
+    pub fn record_snapshot(&mut self, snapshot: InputSnapshot, frames: u32) {
+        let frames = frames.max(1);
+
+        self.frames += 1;
+
+        match &mut self.run {
+            Some((run_snapshot, run_frames, length))
+                if *run_snapshot == snapshot && *run_frames == frames =>
+            {
+                *length += 1;
+            }
+            _ => {
+                self.flush_run();
+                self.run = Some((snapshot, frames, 1));
+            }
+        }
+    }
+
+    ///
+    /// Encodes the recording for storage
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + self.data.len());
+
+        for word in [REPLAY_MAGIC, REPLAY_VERSION, self.seed, self.frames] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&self.data);
+
+        if let Some((snapshot, frames, length)) = self.run {
+            let mut encoded = self.encoded;
+
+            encode_run(&mut bytes, &mut encoded, &snapshot, frames, length);
+        }
+
+        bytes
+    }
+
+    ///
+    /// Writes the recording to slot `bridge_slot_id`, resizing the file to fit
+    ///
+    /// **NOTE:** Writes appear to be broken in the Pocket firmware at the moment. See `docs/control.md`
+    ///
+    pub fn save<B: BridgeBackend>(
+        &self,
+        file: &mut File<B>,
+        bridge_slot_id: u32,
+    ) -> Result<(), BridgeError> {
+        let bytes = self.to_bytes();
+
+        file.set_size(bridge_slot_id, bytes.len() as u32);
+        file.write_from(bridge_slot_id, 0, &bytes)
+    }
+
+    fn flush_run(&mut self) {
+        if let Some((snapshot, frames, length)) = self.run.take() {
+            encode_run(&mut self.data, &mut self.encoded, &snapshot, frames, length);
+        }
+    }
+}
+
+// A run is its length and frames per entry as varints, a mask of the changed registers, and then each changed
+// register
+fn encode_run(
+    bytes: &mut Vec<u8>,
+    encoded: &mut [u32; REGISTER_COUNT],
+    snapshot: &InputSnapshot,
+    frames: u32,
+    length: u32,
+) {
+    let registers = to_registers(snapshot);
+    let mut mask = 0u16;
+
+    for (index, (old, new)) in encoded.iter().zip(&registers).enumerate() {
+        if old != new {
+            mask |= 1 << index;
+        }
+    }
+
+    write_varint(bytes, length);
+    write_varint(bytes, frames);
+    bytes.extend_from_slice(&mask.to_le_bytes());
+
+    for (index, register) in registers.iter().enumerate() {
+        if mask & (1 << index) != 0 {
+            bytes.extend_from_slice(&register.to_le_bytes());
+        }
+    }
+
+    *encoded = registers;
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+/// Plays back a recording made by `InputRecorder`, standing in for `Input` as an `InputSource`.
+///
+/// Call `advance` once per frame, then read input from the playback, using `frame_counter` in place of the live
+/// `VideoStatus::frame_counter`. Load the recording from a bridge slot with `File::read_into`.
+#[derive(Clone)]
+pub struct InputPlayback<'a> {
+    data: &'a [u8],
+    seed: u32,
+    frames: u32,
+    // Read position in `data`, and the current run's remaining entries and frames per entry
+    offset: usize,
+    remaining: u32,
+    run_frames: u32,
+    registers: [u32; REGISTER_COUNT],
+    snapshot: InputSnapshot,
+    position: u32,
+    frame_counter: u32,
+}
+
+impl<'a> InputPlayback<'a> {
+    ///
+    /// Validates the entire recording. Nothing is played until the first `advance`
+    ///
+    pub fn new(data: &'a [u8]) -> Result<Self, ReplayError> {
+        if data.len() < HEADER_LENGTH {
+            return Err(ReplayError::NotReplay);
+        }
+
+        let word = |index: usize| {
+            let bytes = &data[index * 4..index * 4 + 4];
+
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+
+        if word(0) != REPLAY_MAGIC {
+            return Err(ReplayError::NotReplay);
+        }
+
+        if word(1) != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion);
+        }
+
+        let playback = InputPlayback {
+            data,
+            seed: word(2),
+            frames: word(3),
+            offset: HEADER_LENGTH,
+            remaining: 0,
+            run_frames: 0,
+            registers: [0; REGISTER_COUNT],
+            snapshot: InputSnapshot::default(),
+            position: 0,
+            frame_counter: 0,
+        };
+
+        // Walk every run up front, so playback itself can't fail
+        let mut validate = playback.clone();
+        let mut total = 0u32;
+
+        while total < playback.frames {
+            let length = validate.read_run().ok_or(ReplayError::Truncated)?;
+
+            if length == 0 {
+                return Err(ReplayError::Truncated);
+            }
+
+            total = total.saturating_add(length);
+        }
+
+        if total != playback.frames {
+            return Err(ReplayError::Truncated);
+        }
+
+        Ok(playback)
+    }
+
+    ///
+    /// The seed stored with the recording
+    ///
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    ///
+    /// The number of frames in the recording
+    ///
+    pub fn len(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    ///
+    /// The number of frames played so far
+    ///
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.frames
+    }
+
+    ///
+    /// The recorded `VideoStatus::frame_counter` of the current frame, relative to the first frame
+    ///
+    pub fn frame_counter(&self) -> u32 {
+        self.frame_counter
+    }
+
+    ///
+    /// Moves to the next recorded frame. Returns false, leaving the last frame in place, once the recording is finished
+    ///
+    pub fn advance(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        if self.remaining == 0 {
+            match self.read_run() {
+                Some(length) => self.remaining = length,
+                None => {
+                    // Unreachable for a validated recording
+                    self.position = self.frames;
+                    return false;
+                }
+            }
+        }
+
+        self.remaining -= 1;
+        self.position += 1;
+        self.frame_counter = self.frame_counter.wrapping_add(self.run_frames) & FRAME_COUNTER_MASK;
+
+        true
+    }
+
+    ///
+    /// Restarts playback from the first frame
+    ///
+    pub fn rewind(&mut self) {
+        self.offset = HEADER_LENGTH;
+        self.remaining = 0;
+        self.run_frames = 0;
+        self.registers = [0; REGISTER_COUNT];
+        self.snapshot = InputSnapshot::default();
+        self.position = 0;
+        self.frame_counter = 0;
+    }
+
+    // Decodes the run header and registers at `offset`, returning the run's length
+    fn read_run(&mut self) -> Option<u32> {
+        let length = self.read_varint()?;
+        let frames = self.read_varint()?;
+        let mask = u16::from_le_bytes(self.read_bytes::<2>()?);
+
+        for index in 0..REGISTER_COUNT {
+            if mask & (1 << index) != 0 {
+                self.registers[index] = u32::from_le_bytes(self.read_bytes::<4>()?);
+            }
+        }
+
+        self.run_frames = frames;
+        self.snapshot = from_registers(&self.registers);
+
+        Some(length)
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.data.get(self.offset..self.offset + N)?;
+
+        self.offset += N;
+
+        bytes.try_into().ok()
+    }
+
+    fn read_varint(&mut self) -> Option<u32> {
+        let mut value = 0u32;
+
+        for shift in (0..35).step_by(7) {
+            let [byte] = self.read_bytes::<1>()?;
+
+            value |= ((byte & 0x7F) as u32) << shift;
+
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+}
+
+impl InputSource for InputPlayback<'_> {
+    fn key(&self, index: usize) -> u32 {
+        self.snapshot.key[index]
+    }
+
+    fn joy(&self, index: usize) -> u32 {
+        self.snapshot.joy[index]
+    }
+
+    fn trig(&self, index: usize) -> u32 {
+        self.snapshot.trig[index]
+    }
+
+    fn snapshot(&self) -> InputSnapshot {
+        self.snapshot
+    }
+}
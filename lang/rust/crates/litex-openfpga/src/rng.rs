@@ -0,0 +1,151 @@
+use rand_core::{impls, Error, RngCore, SeedableRng};
+
+use crate::{Device, Rtc, Timer};
+
+/// The xoshiro128** 1.1 generator, by David Blackman and Sebastiano Vigna. Fast, small, and good quality, but NOT
+/// suitable for cryptography.
+///
+/// Seed with `from_hardware` for different values every boot, or with `seed_from_u64` for a reproducible sequence,
+/// such as from an `InputRecorder` seed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xoshiro128StarStar {
+    state: [u32; 4],
+}
+
+impl Xoshiro128StarStar {
+    ///
+    /// Seeds from the chip ID, RTC, and uptime, so sequences differ between Pockets and between boots. Uptime is
+    /// counted in clock cycles, so it varies with how long startup took, even when the RTC doesn't
+    ///
+    pub fn from_hardware(device: &Device, rtc: &Rtc, timer: &Timer) -> Self {
+        let mut mix = device.chip_id().0;
+        let mut seed = [0; 16];
+
+        for (chunk, entropy) in seed
+            .chunks_exact_mut(8)
+            .zip([rtc.unix_seconds() as u64, timer.uptime_cycles()])
+        {
+            mix ^= entropy;
+            chunk.copy_from_slice(&splitmix64(&mut mix).to_le_bytes());
+        }
+
+        Self::from_seed(seed)
+    }
+
+    ///
+    /// A uniformly distributed value from 0 to `ceiling`, exclusive. `ceiling` must not be 0
+    ///
+    pub fn below(&mut self, ceiling: u32) -> u32 {
+        assert!(ceiling != 0, "Random ceiling must be non-zero");
+
+        // Lemire's nearly divisionless method, rejecting the biased low products
+        let threshold = ceiling.wrapping_neg() % ceiling;
+
+        loop {
+            let product = self.next_u32() as u64 * ceiling as u64;
+
+            if product as u32 >= threshold {
+                return (product >> 32) as u32;
+            }
+        }
+    }
+
+    ///
+    /// Advances the generator by 2^64 steps, so that sequences split off with `clone` and `jump` never overlap
+    ///
+    pub fn jump(&mut self) {
+        const JUMP: [u32; 4] = [0x8764_000b, 0xf542_d2d3, 0x6fa0_35c3, 0x77f2_db5b];
+
+        let mut state = [0; 4];
+
+        for word in JUMP {
+            for bit in 0..32 {
+                if word & (1 << bit) != 0 {
+                    for (state, current) in state.iter_mut().zip(self.state) {
+                        *state ^= current;
+                    }
+                }
+
+                self.next_u32();
+            }
+        }
+
+        self.state = state;
+    }
+}
+
+impl RngCore for Xoshiro128StarStar {
+    fn next_u32(&mut self) -> u32 {
+        let state = &mut self.state;
+        let result = state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = state[1] << 9;
+
+        state[2] ^= state[0];
+        state[3] ^= state[1];
+        state[1] ^= state[2];
+        state[0] ^= state[3];
+
+        state[2] ^= t;
+
+        state[3] = state[3].rotate_left(11);
+
+        result
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+
+        Ok(())
+    }
+}
+
+impl SeedableRng for Xoshiro128StarStar {
+    type Seed = [u8; 16];
+
+    ///
+    /// Seeds with little endian state words. An all zero seed, which would only ever produce zeros, is replaced
+    ///
+    fn from_seed(seed: [u8; 16]) -> Self {
+        if seed.iter().all(|&byte| byte == 0) {
+            return Self::seed_from_u64(0);
+        }
+
+        let mut state = [0; 4];
+
+        for (word, bytes) in state.iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        Xoshiro128StarStar { state }
+    }
+
+    fn seed_from_u64(mut state: u64) -> Self {
+        let mut seed = [0; 16];
+
+        for chunk in seed.chunks_exact_mut(8) {
+            chunk.copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+        }
+
+        Self::from_seed(seed)
+    }
+}
+
+// Expands `state` into well mixed output, as recommended for seeding the xoshiro generators
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = *state;
+
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    z ^ (z >> 31)
+}
@@ -0,0 +1,430 @@
+use core::fmt;
+use core::time::Duration;
+
+use litex_pac as pac;
+
+use crate::Timer;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// A day of the week.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Sunday,
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+}
+
+impl Weekday {
+    ///
+    /// Days after Sunday, from 0 to 6
+    ///
+    pub const fn number_from_sunday(self) -> u8 {
+        self as u8
+    }
+
+    pub const fn from_number_from_sunday(number: u8) -> Option<Weekday> {
+        match number {
+            0 => Some(Weekday::Sunday),
+            1 => Some(Weekday::Monday),
+            2 => Some(Weekday::Tuesday),
+            3 => Some(Weekday::Wednesday),
+            4 => Some(Weekday::Thursday),
+            5 => Some(Weekday::Friday),
+            6 => Some(Weekday::Saturday),
+            _ => None,
+        }
+    }
+}
+
+pub const fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+///
+/// The number of days in `month`, from 1 to 12, of `year`. 0 for an invalid month
+///
+pub const fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+///
+/// Decodes `digits` packed BCD digits from the low bits of `value`, or `None` if any nibble is above 9
+///
+pub const fn decode_bcd(value: u32, digits: u32) -> Option<u32> {
+    let mut result = 0;
+    let mut digit = digits;
+
+    while digit > 0 {
+        digit -= 1;
+
+        let nibble = (value >> (digit * 4)) & 0xF;
+
+        if nibble > 9 {
+            return None;
+        }
+
+        result = result * 10 + nibble;
+    }
+
+    Some(result)
+}
+
+/// A calendar date in the proleptic Gregorian calendar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    /// From 1 to 12
+    pub month: u8,
+    /// From 1 to 31
+    pub day: u8,
+}
+
+impl Date {
+    ///
+    /// A date, if `month` and `day` exist in `year`
+    ///
+    pub const fn new(year: u16, month: u8, day: u8) -> Option<Date> {
+        if day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        Some(Date { year, month, day })
+    }
+
+    ///
+    /// Decodes a date packed as BCD `0xYYYYMMDD`, as in `APF_RTC.date_bcd`
+    ///
+    pub const fn from_bcd(bcd: u32) -> Option<Date> {
+        let (Some(year), Some(month), Some(day)) = (
+            decode_bcd(bcd >> 16, 4),
+            decode_bcd(bcd >> 8, 2),
+            decode_bcd(bcd, 2),
+        ) else {
+            return None;
+        };
+
+        Date::new(year as u16, month as u8, day as u8)
+    }
+
+    ///
+    /// Days since 1970-01-01, which may be negative
+    ///
+    pub const fn days_since_epoch(&self) -> i32 {
+        // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = self.year as i32 - (self.month <= 2) as i32;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i32;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i32 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    ///
+    /// The date `days` after 1970-01-01
+    ///
+    pub const fn from_days_since_epoch(days: i32) -> Date {
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i32;
+
+        Date {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+
+    pub const fn weekday(&self) -> Weekday {
+        // 1970-01-01 was a Thursday
+        match Weekday::from_number_from_sunday((self.days_since_epoch() + 4).rem_euclid(7) as u8) {
+            Some(weekday) => weekday,
+            None => unreachable!(),
+        }
+    }
+
+    ///
+    /// The day of the year, from 1 to 366
+    ///
+    pub const fn ordinal(&self) -> u16 {
+        let mut ordinal = self.day as u16;
+        let mut month = 1;
+
+        while month < self.month {
+            ordinal += days_in_month(self.year, month) as u16;
+            month += 1;
+        }
+
+        ordinal
+    }
+}
+
+/// A time of day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    /// From 0 to 23
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    pub const fn new(hour: u8, minute: u8, second: u8) -> Option<Time> {
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+
+        Some(Time {
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    ///
+    /// Decodes a time packed as BCD `0xHHMMSS`, as in `APF_RTC.time_bcd`. The top byte is ignored
+    ///
+    pub const fn from_bcd(bcd: u32) -> Option<Time> {
+        let (Some(hour), Some(minute), Some(second)) = (
+            decode_bcd(bcd >> 16, 2),
+            decode_bcd(bcd >> 8, 2),
+            decode_bcd(bcd, 2),
+        ) else {
+            return None;
+        };
+
+        Time::new(hour as u8, minute as u8, second as u8)
+    }
+
+    pub const fn seconds_since_midnight(&self) -> u32 {
+        self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+}
+
+/// A date and time of day, in UTC unless otherwise stated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+impl DateTime {
+    pub const fn from_unix_seconds(seconds: u32) -> DateTime {
+        let time = seconds % SECONDS_PER_DAY;
+
+        DateTime {
+            date: Date::from_days_since_epoch((seconds / SECONDS_PER_DAY) as i32),
+            time: Time {
+                hour: (time / 3600) as u8,
+                minute: (time / 60 % 60) as u8,
+                second: (time % 60) as u8,
+            },
+        }
+    }
+
+    ///
+    /// Seconds since the Unix epoch, or `None` if the date is outside of the range of a `u32`
+    ///
+    pub const fn to_unix_seconds(&self) -> Option<u32> {
+        let seconds = self.date.days_since_epoch() as i64 * SECONDS_PER_DAY as i64
+            + self.time.seconds_since_midnight() as i64;
+
+        if seconds < 0 || seconds > u32::MAX as i64 {
+            None
+        } else {
+            Some(seconds as u32)
+        }
+    }
+
+    pub const fn weekday(&self) -> Weekday {
+        self.date.weekday()
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.date, self.time)
+    }
+}
+
+/// Owned access to the `APF_RTC` block.
+pub struct Rtc {
+    registers: pac::APF_RTC,
+}
+
+impl Rtc {
+    pub fn new(registers: pac::APF_RTC) -> Self {
+        Rtc { registers }
+    }
+
+    pub fn free(self) -> pac::APF_RTC {
+        self.registers
+    }
+
+    ///
+    /// The current Pocket set time, in seconds since the Unix epoch. This value is live
+    ///
+    pub fn unix_seconds(&self) -> u32 {
+        self.registers.unix_seconds.read().bits()
+    }
+
+    ///
+    /// The raw BCD date the Pocket was set to at launch. This value does not update
+    ///
+    pub fn date_bcd(&self) -> u32 {
+        self.registers.date_bcd.read().bits()
+    }
+
+    ///
+    /// The raw BCD time the Pocket was set to at launch. This value does not update
+    ///
+    pub fn time_bcd(&self) -> u32 {
+        self.registers.time_bcd.read().bits()
+    }
+
+    ///
+    /// The current Pocket set date and time, decoded from `unix_seconds`
+    ///
+    pub fn date_time(&self) -> DateTime {
+        DateTime::from_unix_seconds(self.unix_seconds())
+    }
+
+    ///
+    /// The date and time the Pocket was set to at launch, decoded from the BCD registers. `None` if they don't hold a
+    /// valid date and time
+    ///
+    pub fn launch_date_time(&self) -> Option<DateTime> {
+        let (Some(date), Some(time)) = (
+            Date::from_bcd(self.date_bcd()),
+            Time::from_bcd(self.time_bcd()),
+        ) else {
+            return None;
+        };
+
+        Some(DateTime { date, time })
+    }
+}
+
+/// Sub-second wall clock time, combining the whole seconds of `Rtc` with the uptime of `Timer`.
+///
+/// The RTC only counts whole seconds, so the clock watches for it to tick over and measures the fraction of a second
+/// since with the timer. Until the first tick is seen, the fraction is counted from the first observation instead, and
+/// the clock may run up to a second behind. Poll at least once a frame, as the accuracy of the sub-second part is the
+/// interval between polls.
+///
+/// Time never runs backwards while the RTC ticks normally. If the RTC jumps, such as when the user sets the clock, the
+/// clock follows it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WallClock {
+    // The uptime when the RTC reached `last_seconds`
+    anchor_uptime: Duration,
+    last_seconds: u32,
+    last_time: Duration,
+    started: bool,
+    synchronized: bool,
+}
+
+impl WallClock {
+    pub const fn new() -> Self {
+        WallClock {
+            anchor_uptime: Duration::ZERO,
+            last_seconds: 0,
+            last_time: Duration::ZERO,
+            started: false,
+            synchronized: false,
+        }
+    }
+
+    ///
+    /// True once a tick of the RTC has been seen, so the sub-second part is accurate
+    ///
+    pub fn is_synchronized(&self) -> bool {
+        self.synchronized
+    }
+
+    ///
+    /// The current time since the Unix epoch
+    ///
+    pub fn now(&mut self, rtc: &Rtc, timer: &Timer) -> Duration {
+        self.observe(rtc.unix_seconds(), timer.uptime())
+    }
+
+    ///
+    /// The current date and time, and the nanoseconds into the current second
+    ///
+    pub fn date_time(&mut self, rtc: &Rtc, timer: &Timer) -> (DateTime, u32) {
+        let now = self.now(rtc, timer);
+
+        (
+            DateTime::from_unix_seconds(now.as_secs() as u32),
+            now.subsec_nanos(),
+        )
+    }
+
+    ///
+    /// Advances the clock with a reading of `Rtc::unix_seconds` and `Timer::uptime` taken together, returning the time
+    /// since the Unix epoch. `now` calls this with the hardware state
+    ///
+    pub fn observe(&mut self, unix_seconds: u32, uptime: Duration) -> Duration {
+        if !self.started || unix_seconds != self.last_seconds {
+            // A tick from the previous second pins the start of this one. Anything else is a discontinuity
+            let ticked = self.started && unix_seconds == self.last_seconds.wrapping_add(1);
+
+            if !ticked {
+                self.last_time = Duration::ZERO;
+            }
+
+            self.synchronized = ticked;
+            self.anchor_uptime = uptime;
+            self.last_seconds = unix_seconds;
+            self.started = true;
+        }
+
+        let second = Duration::from_secs(unix_seconds as u64);
+        let elapsed = uptime.saturating_sub(self.anchor_uptime);
+        // Never run past the RTC, even if the timer runs fast
+        let time = (second + elapsed).min(second + Duration::from_nanos(999_999_999));
+        let time = time.max(self.last_time);
+
+        self.last_time = time;
+
+        time
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{BridgeBackend, BridgeError, File, ReadError};
+
+// "LXSV"
+const SAVE_MAGIC: u32 = 0x5653_584C;
+
+// Magic, version, sequence, length, and CRC words
+const HEADER_LENGTH: u32 = 5 * 4;
+
+// Size of the stack buffer used to CRC data on disk
+const CHUNK_LENGTH: usize = 256;
+
+/// A failed save or load.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveError {
+    /// The data is larger than the capacity of the save slot
+    TooLarge,
+    /// The buffer is too small to hold the stored save
+    BufferTooSmall,
+    /// The data read back after a write did not match what was written
+    VerifyFailed,
+    /// Reading from the slot failed
+    Read(ReadError),
+    /// Writing to the slot failed
+    Bridge(BridgeError),
+}
+
+impl From<ReadError> for SaveError {
+    fn from(error: ReadError) -> Self {
+        SaveError::Read(error)
+    }
+}
+
+impl From<BridgeError> for SaveError {
+    fn from(error: BridgeError) -> Self {
+        SaveError::Bridge(error)
+    }
+}
+
+/// Information about a successfully loaded save.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaveInfo {
+    /// The application defined version the save was stored with
+    pub version: u32,
+    /// The length of the save data, in bytes
+    pub length: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SaveHeader {
+    version: u32,
+    sequence: u32,
+    length: u32,
+    crc: u32,
+}
+
+impl SaveHeader {
+    fn to_bytes(self) -> [u8; HEADER_LENGTH as usize] {
+        let mut bytes = [0u8; HEADER_LENGTH as usize];
+
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip([
+            SAVE_MAGIC,
+            self.version,
+            self.sequence,
+            self.length,
+            self.crc,
+        ]) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_LENGTH as usize]) -> Option<Self> {
+        let mut words = bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+
+        if words.next() != Some(SAVE_MAGIC) {
+            return None;
+        }
+
+        Some(SaveHeader {
+            version: words.next()?,
+            sequence: words.next()?,
+            length: words.next()?,
+            crc: words.next()?,
+        })
+    }
+
+    ///
+    /// True if this header was written after `other`, accounting for sequence wraparound
+    ///
+    fn is_newer_than(&self, other: &SaveHeader) -> bool {
+        (self.sequence.wrapping_sub(other.sequence) as i32) > 0
+    }
+}
+
+/// A save file stored in a bridge data slot, kept as two alternating copies.
+///
+/// Each copy has a header with a sequence number and a CRC of its data. New saves always overwrite the older copy,
+/// so a torn write only ever damages the copy that was being replaced, and loading falls back to the other.
+///
+/// The data slot must be a writable, nonvolatile slot in `data.json`.
+pub struct SaveSlot {
+    bridge_slot_id: u32,
+    version: u32,
+    capacity: u32,
+    // The header of the newest valid copy and which copy it was, once the slot has been scanned
+    latest: Option<Option<(usize, SaveHeader)>>,
+}
+
+impl SaveSlot {
+    ///
+    /// Creates a save in slot `bridge_slot_id` holding at most `capacity` bytes. `version` is stored with every save
+    ///
+    pub fn new(bridge_slot_id: u32, version: u32, capacity: u32) -> Self {
+        SaveSlot {
+            bridge_slot_id,
+            version,
+            capacity,
+            latest: None,
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    ///
+    /// The total size of the slot's file, covering both copies
+    ///
+    pub fn file_size(&self) -> u32 {
+        2 * self.copy_size()
+    }
+
+    ///
+    /// Loads the newest valid save into `buffer`. Returns `None` if nothing has been saved yet
+    ///
+    pub fn load<B: BridgeBackend>(
+        &mut self,
+        file: &mut File<B>,
+        buffer: &mut [u8],
+    ) -> Result<Option<SaveInfo>, SaveError> {
+        let mut candidates = [
+            (0, self.read_header(file, 0)?),
+            (1, self.read_header(file, 1)?),
+        ];
+
+        // Try the newest copy first
+        if let [(_, Some(first)), (_, Some(second))] = candidates {
+            if second.is_newer_than(&first) {
+                candidates.swap(0, 1);
+            }
+        }
+
+        self.load_ordered(file, buffer, candidates)
+    }
+
+    ///
+    /// Stores `data` over the older of the two copies, then reads it back to verify it
+    ///
+    pub fn store<B: BridgeBackend>(
+        &mut self,
+        file: &mut File<B>,
+        data: &[u8],
+    ) -> Result<(), SaveError> {
+        if data.len() > self.capacity as usize {
+            return Err(SaveError::TooLarge);
+        }
+
+        let latest = match self.latest {
+            Some(latest) => latest,
+            None => self.scan(file)?,
+        };
+
+        let (copy, sequence) = match latest {
+            Some((copy, header)) => (1 - copy, header.sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+
+        let header = SaveHeader {
+            version: self.version,
+            sequence,
+            length: data.len() as u32,
+            crc: crc32(CRC_INITIAL, data),
+        };
+
+        let offset = self.copy_offset(copy);
+
+        // Grow the file first, so neither write lands past its end
+        if file.size(self.bridge_slot_id) < self.file_size() {
+            file.set_size(self.bridge_slot_id, self.file_size());
+        }
+
+        // Write the data before the header, so a partial write can never pair a new header with old data
+        file.write_from(self.bridge_slot_id, offset + HEADER_LENGTH, data)?;
+        file.write_from(self.bridge_slot_id, offset, &header.to_bytes())?;
+
+        // Verify by reading everything back
+        if self.read_header(file, copy)? != Some(header)
+            || self.crc_on_disk(file, offset + HEADER_LENGTH, header.length)? != header.crc
+        {
+            // The copy on disk is now unknown
+            self.latest = None;
+
+            return Err(SaveError::VerifyFailed);
+        }
+
+        self.latest = Some(Some((copy, header)));
+
+        Ok(())
+    }
+
+    fn load_ordered<B: BridgeBackend>(
+        &mut self,
+        file: &mut File<B>,
+        buffer: &mut [u8],
+        candidates: [(usize, Option<SaveHeader>); 2],
+    ) -> Result<Option<SaveInfo>, SaveError> {
+        for (copy, header) in candidates {
+            let Some(header) = header else {
+                continue;
+            };
+
+            let length = header.length as usize;
+
+            if length > buffer.len() {
+                return Err(SaveError::BufferTooSmall);
+            }
+
+            let offset = self.copy_offset(copy) + HEADER_LENGTH;
+            let read = file.read_into(self.bridge_slot_id, offset, &mut buffer[..length])?;
+
+            if read == length && crc32(CRC_INITIAL, &buffer[..length]) == header.crc {
+                self.latest = Some(Some((copy, header)));
+
+                return Ok(Some(SaveInfo {
+                    version: header.version,
+                    length,
+                }));
+            }
+        }
+
+        self.latest = Some(None);
+
+        Ok(None)
+    }
+
+    ///
+    /// Finds the newest valid copy without loading it
+    ///
+    fn scan<B: BridgeBackend>(
+        &mut self,
+        file: &mut File<B>,
+    ) -> Result<Option<(usize, SaveHeader)>, SaveError> {
+        let mut latest: Option<(usize, SaveHeader)> = None;
+
+        for copy in 0..2 {
+            let Some(header) = self.read_header(file, copy)? else {
+                continue;
+            };
+
+            let offset = self.copy_offset(copy) + HEADER_LENGTH;
+
+            if self.crc_on_disk(file, offset, header.length)? != header.crc {
+                continue;
+            }
+
+            match latest {
+                Some((_, newest)) if !header.is_newer_than(&newest) => {}
+                _ => latest = Some((copy, header)),
+            }
+        }
+
+        self.latest = Some(latest);
+
+        Ok(latest)
+    }
+
+    fn read_header<B: BridgeBackend>(
+        &self,
+        file: &mut File<B>,
+        copy: usize,
+    ) -> Result<Option<SaveHeader>, SaveError> {
+        let offset = self.copy_offset(copy);
+
+        if file.size(self.bridge_slot_id) < offset + HEADER_LENGTH {
+            // This copy has never been written
+            return Ok(None);
+        }
+
+        let mut bytes = [0u8; HEADER_LENGTH as usize];
+
+        if file.read_into(self.bridge_slot_id, offset, &mut bytes)? != bytes.len() {
+            return Ok(None);
+        }
+
+        Ok(SaveHeader::from_bytes(&bytes).filter(|header| header.length <= self.capacity))
+    }
+
+    fn crc_on_disk<B: BridgeBackend>(
+        &self,
+        file: &mut File<B>,
+        offset: u32,
+        length: u32,
+    ) -> Result<u32, SaveError> {
+        let mut chunk = [0u8; CHUNK_LENGTH];
+        let mut crc = CRC_INITIAL;
+        let mut position = 0;
+
+        while position < length {
+            let chunk_length = (length - position).min(CHUNK_LENGTH as u32) as usize;
+            let read = file.read_into(
+                self.bridge_slot_id,
+                offset + position,
+                &mut chunk[..chunk_length],
+            )?;
+
+            if read == 0 {
+                // Truncated file, so this can't match
+                return Ok(!crc);
+            }
+
+            crc = crc32_update(crc, &chunk[..read]);
+            position += read as u32;
+        }
+
+        Ok(!crc)
+    }
+
+    fn copy_size(&self) -> u32 {
+        // Keep each copy word aligned
+        HEADER_LENGTH + ((self.capacity + 3) & !0x3)
+    }
+
+    fn copy_offset(&self, copy: usize) -> u32 {
+        copy as u32 * self.copy_size()
+    }
+}
+
+const CRC_INITIAL: u32 = 0xFFFF_FFFF;
+
+// Reflected CRC-32 (IEEE 802.3) lookup table
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+};
+
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, byte| {
+        CRC_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    !crc32_update(crc, bytes)
+}
@@ -0,0 +1,600 @@
+use alloc::{vec, vec::Vec};
+use core::slice::from_raw_parts_mut;
+
+use crate::{
+    BlitMode, BridgeBackend, BufferDamage, Canvas, DirtyRegions, DrawMode, File, Flip,
+    Framebuffers, ReadError, Rect, Rgb565, VideoBackend,
+};
+
+/// A tile index that draws nothing
+pub const EMPTY_TILE: u16 = u16::MAX;
+
+// The most frames of dirty regions kept, and so the oldest buffer that can be partially redrawn
+const MAX_BUFFER_AGE: usize = 8;
+
+// Past this many changed tiles in a frame, the whole layer is redrawn
+const MAX_CHANGED_TILES: usize = 64;
+
+/// A sheet of equally sized tiles, drawn by `TileLayer`s and `Sprite`s. Tiles are numbered left to right, then top to
+/// bottom.
+pub struct Tileset {
+    pixels: Vec<Rgb565>,
+    sheet_width: usize,
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    count: u32,
+    color_key: Option<Rgb565>,
+}
+
+impl Tileset {
+    ///
+    /// A tileset from a sheet of `pixels`, `sheet_width` pixels wide. Partial tiles at the right and bottom edges are
+    /// ignored
+    ///
+    pub fn new(pixels: Vec<Rgb565>, sheet_width: usize, tile_width: u32, tile_height: u32) -> Self {
+        assert!(
+            tile_width != 0 && tile_height != 0 && sheet_width >= tile_width as usize,
+            "Tiles must be non-empty, and fit within the sheet"
+        );
+
+        let columns = (sheet_width / tile_width as usize) as u32;
+        let rows = (pixels.len() / sheet_width) as u32 / tile_height;
+
+        Tileset {
+            pixels,
+            sheet_width,
+            tile_width,
+            tile_height,
+            columns,
+            count: columns * rows,
+            color_key: None,
+        }
+    }
+
+    ///
+    /// Loads a sheet of raw little endian RGB565 pixels, `sheet_width` pixels wide, from slot `bridge_slot_id`
+    ///
+    pub fn load<B: BridgeBackend>(
+        file: &mut File<B>,
+        bridge_slot_id: u32,
+        sheet_width: usize,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Result<Self, ReadError> {
+        let mut pixels = vec![Rgb565::BLACK; file.size(bridge_slot_id) as usize / 2];
+        // `Rgb565` is a transparent `u16`, which is little endian on the Pocket
+        let bytes = unsafe { from_raw_parts_mut(pixels.as_mut_ptr() as *mut u8, pixels.len() * 2) };
+
+        file.read_into(bridge_slot_id, 0, bytes)?;
+
+        Ok(Tileset::new(pixels, sheet_width, tile_width, tile_height))
+    }
+
+    ///
+    /// Makes pixels of `key` transparent
+    ///
+    pub fn with_color_key(mut self, key: Rgb565) -> Self {
+        self.color_key = Some(key);
+        self
+    }
+
+    pub fn color_key(&self) -> Option<Rgb565> {
+        self.color_key
+    }
+
+    pub fn tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    pub fn tile_height(&self) -> u32 {
+        self.tile_height
+    }
+
+    ///
+    /// The number of tiles in the sheet
+    ///
+    pub fn len(&self) -> u32 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    ///
+    /// The area of `tile` within the sheet
+    ///
+    pub fn tile_region(&self, tile: u16) -> Rect {
+        let tile = tile as u32;
+
+        Rect::new(
+            ((tile % self.columns) * self.tile_width) as i32,
+            ((tile / self.columns) * self.tile_height) as i32,
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+
+    ///
+    /// Draws `tile` with its top left corner at `(x, y)`. `EMPTY_TILE` and tiles past the end of the sheet draw nothing
+    ///
+    pub fn draw_tile(&self, canvas: &mut Canvas, tile: u16, x: i32, y: i32, flip: Flip) {
+        if tile as u32 >= self.count {
+            return;
+        }
+
+        let mode = match self.color_key {
+            Some(key) => BlitMode::ColorKey(key),
+            None => BlitMode::Copy,
+        };
+
+        canvas.blit_region_flipped(
+            &self.pixels,
+            self.sheet_width,
+            self.tile_region(tile),
+            x,
+            y,
+            mode,
+            flip,
+        );
+    }
+}
+
+/// A grid of tiles from one `Tileset`, scrolled as a whole.
+pub struct TileLayer {
+    tileset: usize,
+    width: u32,
+    height: u32,
+    tiles: Vec<u16>,
+    scroll_x: i32,
+    scroll_y: i32,
+    wrap: bool,
+    visible: bool,
+    // Tiles changed since the last render, or `None` if the whole layer needs redrawing
+    changed: Option<Vec<(u32, u32)>>,
+}
+
+impl TileLayer {
+    ///
+    /// A layer of `width` by `height` tiles from tileset `tileset` of the `Scene`, all `EMPTY_TILE`
+    ///
+    pub fn new(tileset: usize, width: u32, height: u32) -> Self {
+        TileLayer {
+            tileset,
+            width,
+            height,
+            tiles: vec![EMPTY_TILE; (width * height) as usize],
+            scroll_x: 0,
+            scroll_y: 0,
+            wrap: false,
+            visible: true,
+            changed: None,
+        }
+    }
+
+    ///
+    /// Repeats the layer endlessly in both directions, rather than leaving empty space around it
+    ///
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn tileset(&self) -> usize {
+        self.tileset
+    }
+
+    ///
+    /// The width of the layer, in tiles
+    ///
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    ///
+    /// The height of the layer, in tiles
+    ///
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> u16 {
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: u16) {
+        let index = (y * self.width + x) as usize;
+
+        if self.tiles[index] == tile {
+            return;
+        }
+
+        self.tiles[index] = tile;
+
+        if let Some(changed) = &mut self.changed {
+            if changed.len() < MAX_CHANGED_TILES {
+                changed.push((x, y));
+            } else {
+                self.changed = None;
+            }
+        }
+    }
+
+    ///
+    /// Sets every tile, from rows of `width` tiles
+    ///
+    pub fn set_tiles(&mut self, tiles: &[u16]) {
+        self.tiles.copy_from_slice(tiles);
+        self.changed = None;
+    }
+
+    pub fn fill(&mut self, tile: u16) {
+        self.tiles.fill(tile);
+        self.changed = None;
+    }
+
+    ///
+    /// The layer position shown at the top left of the screen, in pixels
+    ///
+    pub fn scroll(&self) -> (i32, i32) {
+        (self.scroll_x, self.scroll_y)
+    }
+
+    pub fn set_scroll(&mut self, x: i32, y: i32) {
+        if (x, y) != (self.scroll_x, self.scroll_y) {
+            self.scroll_x = x;
+            self.scroll_y = y;
+            self.changed = None;
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible != self.visible {
+            self.visible = visible;
+            self.changed = None;
+        }
+    }
+
+    // Marks this frame's changes in `dirty`, and starts tracking changes afresh
+    fn take_changes(&mut self, tileset: &Tileset, dirty: &mut DirtyRegions) {
+        let Some(changed) = self.changed.replace(Vec::new()) else {
+            dirty.add_all();
+            return;
+        };
+
+        if !self.visible {
+            return;
+        }
+
+        let (tile_width, tile_height) = (tileset.tile_width as i32, tileset.tile_height as i32);
+        let bounds = dirty.bounds();
+
+        for (x, y) in changed {
+            let left = x as i32 * tile_width - self.scroll_x;
+            let top = y as i32 * tile_height - self.scroll_y;
+
+            if !self.wrap {
+                dirty.add(Rect::new(
+                    left,
+                    top,
+                    tileset.tile_width,
+                    tileset.tile_height,
+                ));
+                continue;
+            }
+
+            // Every on screen repeat of the tile
+            let period_x = self.width as i32 * tile_width;
+            let period_y = self.height as i32 * tile_height;
+            let mut repeat_y = bounds.y + (top - bounds.y).rem_euclid(period_y) - period_y;
+
+            while repeat_y < bounds.bottom() {
+                let mut repeat_x = bounds.x + (left - bounds.x).rem_euclid(period_x) - period_x;
+
+                while repeat_x < bounds.right() {
+                    dirty.add(Rect::new(
+                        repeat_x,
+                        repeat_y,
+                        tileset.tile_width,
+                        tileset.tile_height,
+                    ));
+
+                    repeat_x += period_x;
+                }
+
+                repeat_y += period_y;
+            }
+        }
+    }
+
+    fn draw(&self, tileset: &Tileset, canvas: &mut Canvas) {
+        let clip = canvas.clip();
+
+        if !self.visible || clip.is_empty() || self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let (tile_width, tile_height) = (tileset.tile_width as i32, tileset.tile_height as i32);
+        let first_x = (clip.x + self.scroll_x).div_euclid(tile_width);
+        let last_x = (clip.right() - 1 + self.scroll_x).div_euclid(tile_width);
+        let first_y = (clip.y + self.scroll_y).div_euclid(tile_height);
+        let last_y = (clip.bottom() - 1 + self.scroll_y).div_euclid(tile_height);
+
+        for tile_y in first_y..=last_y {
+            for tile_x in first_x..=last_x {
+                let (x, y) = if self.wrap {
+                    (
+                        tile_x.rem_euclid(self.width as i32),
+                        tile_y.rem_euclid(self.height as i32),
+                    )
+                } else if tile_x < 0
+                    || tile_y < 0
+                    || tile_x >= self.width as i32
+                    || tile_y >= self.height as i32
+                {
+                    continue;
+                } else {
+                    (tile_x, tile_y)
+                };
+
+                tileset.draw_tile(
+                    canvas,
+                    self.tile(x as u32, y as u32),
+                    tile_x * tile_width - self.scroll_x,
+                    tile_y * tile_height - self.scroll_y,
+                    Flip::NONE,
+                );
+            }
+        }
+    }
+}
+
+/// One tile from a `Tileset`, drawn in screen coordinates above every `TileLayer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    pub tileset: usize,
+    pub tile: u16,
+    pub x: i32,
+    pub y: i32,
+    pub flip: Flip,
+    pub visible: bool,
+}
+
+impl Sprite {
+    pub const fn new(tileset: usize, tile: u16, x: i32, y: i32) -> Self {
+        Sprite {
+            tileset,
+            tile,
+            x,
+            y,
+            flip: Flip::NONE,
+            visible: true,
+        }
+    }
+
+    fn bounds(&self, tilesets: &[Tileset]) -> Rect {
+        let tileset = &tilesets[self.tileset];
+
+        if self.visible {
+            Rect::new(self.x, self.y, tileset.tile_width, tileset.tile_height)
+        } else {
+            Rect::default()
+        }
+    }
+}
+
+/// Tile layers and sprites drawn on the CPU, redrawing only what changed since a buffer was last drawn.
+///
+/// Layers are drawn in the order they were added, then sprites in the order they were added, over a background color.
+/// Each render works out what changed from the sprites as last drawn and the tiles, scroll, and visibility changed on
+/// each layer.
+///
+/// ```ignore
+/// let mut framebuffers = Framebuffers::<2>::new(pocket.video);
+/// let mut scene = Scene::new(FRAMEBUFFER_WIDTH as u32, FRAMEBUFFER_HEIGHT as u32);
+///
+/// let tiles = scene.add_tileset(Tileset::load(&mut pocket.bridge, 1, 128, 8, 8)?.with_color_key(Rgb565(0xF81F)));
+/// let background = scene.add_layer(TileLayer::new(tiles, 64, 32).with_wrap(true));
+/// let player = scene.add_sprite(Sprite::new(tiles, 12, 100, 100));
+///
+/// loop {
+///     scene.sprite_mut(player).x += 1;
+///     scene.present(&mut framebuffers);
+/// }
+/// ```
+pub struct Scene {
+    width: u32,
+    height: u32,
+    background: Rgb565,
+    tilesets: Vec<Tileset>,
+    layers: Vec<TileLayer>,
+    sprites: Vec<Sprite>,
+    // Sprites as of the last render, to find what moved
+    drawn_sprites: Vec<Sprite>,
+    // The dirty regions of recent renders
+    damage: BufferDamage<MAX_BUFFER_AGE>,
+    invalidated: bool,
+}
+
+impl Scene {
+    ///
+    /// An empty scene covering a `width` by `height` pixel screen
+    ///
+    pub fn new(width: u32, height: u32) -> Self {
+        Scene {
+            width,
+            height,
+            background: Rgb565::BLACK,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            sprites: Vec::new(),
+            drawn_sprites: Vec::new(),
+            damage: BufferDamage::new(),
+            invalidated: true,
+        }
+    }
+
+    pub fn with_background(mut self, background: Rgb565) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn background(&self) -> Rgb565 {
+        self.background
+    }
+
+    pub fn set_background(&mut self, background: Rgb565) {
+        if background != self.background {
+            self.background = background;
+            self.invalidated = true;
+        }
+    }
+
+    ///
+    /// Adds a tileset, returning its index
+    ///
+    pub fn add_tileset(&mut self, tileset: Tileset) -> usize {
+        self.tilesets.push(tileset);
+        self.tilesets.len() - 1
+    }
+
+    pub fn tileset(&self, index: usize) -> &Tileset {
+        &self.tilesets[index]
+    }
+
+    ///
+    /// Adds a layer above all others, returning its index
+    ///
+    pub fn add_layer(&mut self, layer: TileLayer) -> usize {
+        assert!(layer.tileset < self.tilesets.len(), "Unknown tileset");
+
+        self.invalidated = true;
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
+
+    pub fn layer(&self, index: usize) -> &TileLayer {
+        &self.layers[index]
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> &mut TileLayer {
+        &mut self.layers[index]
+    }
+
+    ///
+    /// Adds a sprite above all others, returning its index
+    ///
+    pub fn add_sprite(&mut self, sprite: Sprite) -> usize {
+        assert!(sprite.tileset < self.tilesets.len(), "Unknown tileset");
+
+        self.sprites.push(sprite);
+        self.sprites.len() - 1
+    }
+
+    pub fn sprite(&self, index: usize) -> &Sprite {
+        &self.sprites[index]
+    }
+
+    pub fn sprite_mut(&mut self, index: usize) -> &mut Sprite {
+        &mut self.sprites[index]
+    }
+
+    pub fn sprites_mut(&mut self) -> &mut [Sprite] {
+        &mut self.sprites
+    }
+
+    ///
+    /// Redraws everything on the next render, such as after drawing over the scene
+    ///
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    ///
+    /// Brings `canvas` up to date, given that it was last rendered `buffer_age` renders ago, and returns the regions
+    /// redrawn. An age of 0 means unknown, and redraws everything. With `Framebuffers<N>`, the age is `N`
+    ///
+    pub fn render(&mut self, canvas: &mut Canvas, buffer_age: usize) -> DirtyRegions {
+        let mut dirty = DirtyRegions::new(
+            Rect::new(0, 0, self.width, self.height).intersection(&canvas.bounds()),
+        );
+
+        self.collect_changes(&mut dirty);
+        self.damage.record_render(dirty);
+
+        let region = self.damage.damage_since(buffer_age);
+
+        let mode = canvas.mode();
+
+        canvas.set_mode(DrawMode::Replace);
+
+        for &rect in region.rects() {
+            canvas.set_clip(rect);
+            canvas.fill_rect(rect, self.background);
+
+            for layer in &self.layers {
+                layer.draw(&self.tilesets[layer.tileset], canvas);
+            }
+
+            for sprite in &self.sprites {
+                if sprite.visible && sprite.bounds(&self.tilesets).intersects(&rect) {
+                    self.tilesets[sprite.tileset].draw_tile(
+                        canvas,
+                        sprite.tile,
+                        sprite.x,
+                        sprite.y,
+                        sprite.flip,
+                    );
+                }
+            }
+        }
+
+        canvas.reset_clip();
+        canvas.set_mode(mode);
+
+        region
+    }
+
+    ///
+    /// Renders into the back buffer of `framebuffers` and presents it, returning the frame counter from
+    /// `Framebuffers::present`
+    ///
+    pub fn present<const N: usize, V: VideoBackend>(
+        &mut self,
+        framebuffers: &mut Framebuffers<N, V>,
+    ) -> u32 {
+        self.render(&mut framebuffers.canvas(), N);
+
+        framebuffers.present()
+    }
+
+    fn collect_changes(&mut self, dirty: &mut DirtyRegions) {
+        if self.invalidated {
+            self.invalidated = false;
+            dirty.add_all();
+        }
+
+        for layer in &mut self.layers {
+            layer.take_changes(&self.tilesets[layer.tileset], dirty);
+        }
+
+        for (index, sprite) in self.sprites.iter().enumerate() {
+            match self.drawn_sprites.get(index) {
+                Some(drawn) if drawn == sprite => {}
+                Some(drawn) => {
+                    dirty.add(drawn.bounds(&self.tilesets));
+                    dirty.add(sprite.bounds(&self.tilesets));
+                }
+                None => dirty.add(sprite.bounds(&self.tilesets)),
+            }
+        }
+
+        self.drawn_sprites.clear();
+        self.drawn_sprites.extend_from_slice(&self.sprites);
+    }
+}
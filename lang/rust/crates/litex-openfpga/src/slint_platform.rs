@@ -1,23 +1,205 @@
-use alloc::rc::Rc;
-use num_traits::float::Float;
-use slint::platform::{software_renderer::MinimalSoftwareWindow, Platform};
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::cell::RefCell;
 
-pub struct SlintPlatform {
-    pub window: Rc<MinimalSoftwareWindow>,
-    clock_cycle_period_nanos: f64,
+use critical_section::Mutex;
+use slint::{
+    platform::{
+        software_renderer::{
+            MinimalSoftwareWindow, RepaintBufferType, Rgb565Pixel, SoftwareRenderer,
+        },
+        EventLoopProxy, Key, Platform, WindowEvent,
+    },
+    EventLoopError, PlatformError, SharedString,
+};
+
+use crate::{
+    BufferDamage, Buttons, FrameClock, Framebuffers, InputSource, InputState, Rect, RedrawStats,
+    Rgb565, Timer, Video, DEFAULT_REPEAT_DELAY, DEFAULT_REPEAT_RATE, FRAMEBUFFER_WIDTH,
+};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+// Requests from `EventLoopProxy`, which may be made from interrupts
+static EVENT_QUEUE: Mutex<RefCell<EventQueue>> = Mutex::new(RefCell::new(EventQueue {
+    callbacks: Vec::new(),
+    quit: false,
+}));
+
+struct EventQueue {
+    callbacks: Vec<Callback>,
+    quit: bool,
+}
+
+/// Maps controller buttons to the Slint keys dispatched by `SlintPlatform::run_event_loop`.
+///
+/// By default, the d-pad of controller 0 is the arrow keys, A is Return, and B is Escape. Held buttons auto-repeat
+/// like a keyboard.
+#[derive(Clone, Debug)]
+pub struct KeyMap {
+    controller: usize,
+    bindings: Vec<(Buttons, char)>,
+    repeat_delay: u32,
+    repeat_rate: u32,
+}
+
+impl KeyMap {
+    ///
+    /// A map for `controller` with no bindings
+    ///
+    pub fn new(controller: usize) -> Self {
+        KeyMap {
+            controller,
+            bindings: Vec::new(),
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_rate: DEFAULT_REPEAT_RATE,
+        }
+    }
+
+    ///
+    /// Sends `key`, a `slint::platform::Key` or any character, while `button` is held. Replaces any existing binding
+    /// of `button`
+    ///
+    pub fn with_binding(mut self, button: Buttons, key: impl Into<char>) -> Self {
+        self.bindings.retain(|&(bound, _)| bound != button);
+        self.bindings.push((button, key.into()));
+        self
+    }
+
+    ///
+    /// Sets the auto-repeat timing, in frames. A `rate` of 0 disables repeating
+    ///
+    pub fn with_repeat(mut self, delay: u32, rate: u32) -> Self {
+        self.repeat_delay = delay;
+        self.repeat_rate = rate;
+        self
+    }
+
+    pub fn controller(&self) -> usize {
+        self.controller
+    }
+
+    ///
+    /// The key bound to `button`, if any
+    ///
+    pub fn key(&self, button: Buttons) -> Option<char> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == button)
+            .map(|&(_, key)| key)
+    }
+
+    // Dispatches a key event to `window` for every binding that changed in the last `input` update
+    fn dispatch(&self, input: &InputState, window: &MinimalSoftwareWindow) {
+        for &(button, key) in &self.bindings {
+            let mut text = [0; 4];
+            let text = SharedString::from(&*key.encode_utf8(&mut text));
+
+            if input.repeated(self.controller, button) {
+                window.dispatch_event(WindowEvent::KeyPressed { text: text.clone() });
+            }
+
+            if input.just_released(self.controller, button) {
+                window.dispatch_event(WindowEvent::KeyReleased { text });
+            }
+        }
+    }
 }
 
-fn combine_u32(low: u32, high: u32) -> u64 {
-    ((high as u64) << 32) | (low as u64)
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap::new(0)
+            .with_binding(Buttons::DPAD_UP, Key::UpArrow)
+            .with_binding(Buttons::DPAD_DOWN, Key::DownArrow)
+            .with_binding(Buttons::DPAD_LEFT, Key::LeftArrow)
+            .with_binding(Buttons::DPAD_RIGHT, Key::RightArrow)
+            .with_binding(Buttons::FACE_A, Key::Return)
+            .with_binding(Buttons::FACE_B, Key::Escape)
+    }
+}
+
+// Type erases `SlintFramebuffers` for any number of buffers
+trait EventLoopDisplay {
+    fn video(&self) -> &Video;
+
+    fn poll(&mut self);
+
+    fn draw_if_needed(&mut self, window: &MinimalSoftwareWindow) -> bool;
+}
+
+impl<const N: usize> EventLoopDisplay for SlintFramebuffers<N> {
+    fn video(&self) -> &Video {
+        self.framebuffers.video()
+    }
+
+    fn poll(&mut self) {
+        self.framebuffers.poll();
+    }
+
+    fn draw_if_needed(&mut self, window: &MinimalSoftwareWindow) -> bool {
+        SlintFramebuffers::draw_if_needed(self, window)
+    }
+}
+
+struct EventLoop {
+    display: Box<dyn EventLoopDisplay>,
+    input: Box<dyn InputSource>,
+    input_state: InputState,
+    clock: FrameClock,
+}
+
+/// The Slint `Platform` for the Pocket, with one window sized to the display.
+///
+/// Given `with_event_loop`, `run_event_loop` runs Slint once per vblank: controller input is dispatched as key
+/// events through the `KeyMap`, `slint::Timer`s and animations are updated, and the window is rendered into the
+/// framebuffers if needed. Without it, the app must run its own loop around `update_timers_and_animations` and
+/// `MinimalSoftwareWindow::draw_if_needed`.
+pub struct SlintPlatform {
+    pub window: Rc<MinimalSoftwareWindow>,
+    timer: Timer,
+    key_map: KeyMap,
+    event_loop: RefCell<Option<EventLoop>>,
 }
 
 impl SlintPlatform {
-    pub fn new(window: Rc<MinimalSoftwareWindow>, clock_speed_hz: u32) -> Self {
+    pub fn new(window: Rc<MinimalSoftwareWindow>, timer: Timer) -> Self {
         SlintPlatform {
             window,
-            clock_cycle_period_nanos: 1_000_000_000.0 / (clock_speed_hz as f64),
+            timer,
+            key_map: KeyMap::default(),
+            event_loop: RefCell::new(None),
         }
     }
+
+    ///
+    /// Lets `run_event_loop` render into `framebuffers`, reading controllers from `input`, usually `Input` or an
+    /// `InputPlayback`. The window must be created by `SlintFramebuffers::create_window`
+    ///
+    pub fn with_event_loop<const N: usize>(
+        self,
+        framebuffers: SlintFramebuffers<N>,
+        input: impl InputSource + 'static,
+    ) -> Self {
+        self.event_loop.replace(Some(EventLoop {
+            display: Box::new(framebuffers),
+            input: Box::new(input),
+            input_state: InputState::new(),
+            clock: FrameClock::new(),
+        }));
+
+        self
+    }
+
+    ///
+    /// Replaces the default `KeyMap`
+    ///
+    pub fn with_key_map(mut self, key_map: KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    pub fn key_map(&self) -> &KeyMap {
+        &self.key_map
+    }
 }
 
 impl Platform for SlintPlatform {
@@ -30,24 +212,193 @@ impl Platform for SlintPlatform {
     }
 
     fn duration_since_start(&self) -> core::time::Duration {
-        let peripherals = unsafe { litex_pac::Peripherals::steal() };
+        self.timer.uptime()
+    }
 
-        unsafe {
-            // Grab cycle count
-            peripherals.TIMER0.uptime_latch.write(|w| w.bits(1));
+    fn run_event_loop(&self) -> Result<(), PlatformError> {
+        // Fails if called from within the running event loop
+        let Ok(mut event_loop) = self.event_loop.try_borrow_mut() else {
+            return Err(PlatformError::Other(
+                "The event loop is already running".into(),
+            ));
         };
+        let Some(event_loop) = event_loop.as_mut() else {
+            return Err(PlatformError::Other(
+                "SlintPlatform needs with_event_loop to run the event loop".into(),
+            ));
+        };
+
+        event_loop
+            .input_state
+            .set_repeat(self.key_map.repeat_delay, self.key_map.repeat_rate);
+
+        // A quit requested before running is for a previous run
+        critical_section::with(|cs| EVENT_QUEUE.borrow_ref_mut(cs).quit = false);
+
+        loop {
+            event_loop.clock.wait_for_vblank(event_loop.display.video());
+            // With more than 2 buffers, a queued frame is waiting for this vblank
+            event_loop.display.poll();
+
+            event_loop
+                .input_state
+                .update(&*event_loop.input, event_loop.clock.frame_counter());
+            self.key_map.dispatch(&event_loop.input_state, &self.window);
+
+            let (callbacks, quit) = critical_section::with(|cs| {
+                let mut queue = EVENT_QUEUE.borrow_ref_mut(cs);
+                let quit = core::mem::replace(&mut queue.quit, false);
+
+                (core::mem::take(&mut queue.callbacks), quit)
+            });
+
+            for callback in callbacks {
+                callback();
+            }
+
+            if quit {
+                return Ok(());
+            }
+
+            slint::platform::update_timers_and_animations();
+            event_loop.display.draw_if_needed(&self.window);
+        }
+    }
+
+    fn new_event_loop_proxy(&self) -> Option<Box<dyn EventLoopProxy>> {
+        Some(Box::new(PocketEventLoopProxy))
+    }
+}
+
+// Queues requests for `SlintPlatform::run_event_loop`, picked up at the next vblank
+struct PocketEventLoopProxy;
+
+impl EventLoopProxy for PocketEventLoopProxy {
+    fn quit_event_loop(&self) -> Result<(), EventLoopError> {
+        critical_section::with(|cs| EVENT_QUEUE.borrow_ref_mut(cs).quit = true);
+
+        Ok(())
+    }
+
+    fn invoke_from_event_loop(&self, event: Callback) -> Result<(), EventLoopError> {
+        critical_section::with(|cs| EVENT_QUEUE.borrow_ref_mut(cs).callbacks.push(event));
+
+        Ok(())
+    }
+}
+
+/// Renders Slint into `Framebuffers`, redrawing only what changed.
+///
+/// With `RepaintBufferType::SwappedBuffers`, which needs exactly 2 buffers, Slint redraws the damage of both the last
+/// frame and this one. With `ReusedBuffer`, Slint only redraws this frame's damage, so the rows each buffer missed
+/// while others were drawn are first copied from the latest frame. `NewBuffer` redraws everything, every frame.
+pub struct SlintFramebuffers<const N: usize> {
+    framebuffers: Framebuffers<N>,
+    repaint_buffer_type: RepaintBufferType,
+    damage: BufferDamage<N>,
+}
 
-        let low_bits = peripherals.TIMER0.uptime_cycles0.read().bits();
-        let high_bits = peripherals.TIMER0.uptime_cycles1.read().bits();
-        let uptime_cycles = combine_u32(low_bits, high_bits);
+impl<const N: usize> SlintFramebuffers<N> {
+    ///
+    /// Uses `SwappedBuffers` with 2 buffers, and `ReusedBuffer` with more
+    ///
+    pub fn new(framebuffers: Framebuffers<N>) -> Self {
+        let repaint_buffer_type = if N == 2 {
+            RepaintBufferType::SwappedBuffers
+        } else {
+            RepaintBufferType::ReusedBuffer
+        };
+
+        SlintFramebuffers {
+            framebuffers,
+            repaint_buffer_type,
+            damage: BufferDamage::new(),
+        }
+    }
+
+    ///
+    /// Overrides the repaint buffer type. `SwappedBuffers` requires `N` to be 2
+    ///
+    pub fn with_repaint_buffer_type(mut self, repaint_buffer_type: RepaintBufferType) -> Self {
+        assert!(
+            !matches!(repaint_buffer_type, RepaintBufferType::SwappedBuffers) || N == 2,
+            "SwappedBuffers needs exactly 2 framebuffers"
+        );
+
+        self.repaint_buffer_type = repaint_buffer_type;
+        self
+    }
+
+    pub fn free(self) -> Framebuffers<N> {
+        self.framebuffers
+    }
+
+    pub fn framebuffers(&mut self) -> &mut Framebuffers<N> {
+        &mut self.framebuffers
+    }
+
+    ///
+    /// The repaint buffer type the window must be created with
+    ///
+    pub fn repaint_buffer_type(&self) -> RepaintBufferType {
+        self.repaint_buffer_type
+    }
 
-        let duration = (self.clock_cycle_period_nanos * (uptime_cycles as f64)).floor() as u64;
+    ///
+    /// Creates a window that renders with `repaint_buffer_type`
+    ///
+    pub fn create_window(&self) -> Rc<MinimalSoftwareWindow> {
+        MinimalSoftwareWindow::new(self.repaint_buffer_type)
+    }
+
+    ///
+    /// Renders and presents a frame if `window` needs redrawing. Returns true if it did
+    ///
+    pub fn draw_if_needed(&mut self, window: &MinimalSoftwareWindow) -> bool {
+        window.draw_if_needed(|renderer| {
+            self.render(renderer);
+        })
+    }
+
+    ///
+    /// Renders into the back buffer and presents it, for use within `MinimalSoftwareWindow::draw_if_needed`. Returns
+    /// the region Slint redrew
+    ///
+    pub fn render(&mut self, renderer: &SoftwareRenderer) -> Rect {
+        let back = self.framebuffers.back_index();
+
+        if matches!(self.repaint_buffer_type, RepaintBufferType::ReusedBuffer) {
+            if let Some(latest) = self.damage.latest() {
+                let rows = self.damage.stale_rows(back);
+
+                self.framebuffers.copy_rows_to_back(latest, rows.clone());
+                self.damage.record_copy(rows);
+            }
+        }
+
+        let buffer = self.framebuffers.back_buffer();
+        // `Rgb565` and `Rgb565Pixel` are both transparent wrappers of the raw `u16`
+        let buffer = unsafe { &mut *(buffer as *mut [Rgb565] as *mut [Rgb565Pixel]) };
+
+        let region = renderer.render(buffer, FRAMEBUFFER_WIDTH);
+        let origin = region.bounding_box_origin();
+        let size = region.bounding_box_size();
+        let redrawn = Rect::new(origin.x, origin.y, size.width, size.height);
+
+        self.damage.record(back, redrawn);
+        self.framebuffers.present();
+
+        redrawn
+    }
 
-        core::time::Duration::from_nanos(duration)
+    ///
+    /// How much has been redrawn since the last `reset_stats`
+    ///
+    pub fn stats(&self) -> RedrawStats {
+        self.damage.stats()
     }
 
-    // optional: You can put the event loop there, or in the main function, see later
-    fn run_event_loop(&self) -> Result<(), slint::PlatformError> {
-        todo!();
+    pub fn reset_stats(&mut self) {
+        self.damage.reset_stats();
     }
 }
@@ -0,0 +1,174 @@
+use embedded_io::{BufRead, ErrorKind, ErrorType, Read, Seek, SeekFrom};
+
+use litex_pac as pac;
+
+use crate::{BridgeBackend, File, ReadError};
+
+/// The default size of the `SlotReader` RAM buffer
+pub const DEFAULT_SLOT_BUFFER_SIZE: usize = 512;
+
+// Word aligned so the bridge can transfer directly into the buffer
+#[repr(align(4))]
+struct SlotBuffer<const N: usize>([u8; N]);
+
+/// A buffered, seekable `embedded_io` reader over a single bridge data slot.
+///
+/// Bridge reads are issued on demand, `N` bytes at a time. Reads at least as large as the buffer bypass it entirely.
+pub struct SlotReader<
+    'a,
+    const N: usize = DEFAULT_SLOT_BUFFER_SIZE,
+    B: BridgeBackend = pac::APF_BRIDGE,
+> {
+    file: &'a mut File<B>,
+    bridge_slot_id: u32,
+    size: u32,
+    buffer: SlotBuffer<N>,
+    // File offset of the start of `buffer`
+    buffer_offset: u32,
+    // Read position in `buffer`
+    position: usize,
+    // Number of valid bytes in `buffer`
+    filled: usize,
+}
+
+impl<'a, const N: usize, B: BridgeBackend> SlotReader<'a, N, B> {
+    pub fn new(file: &'a mut File<B>, bridge_slot_id: u32) -> Self {
+        let size = file.size(bridge_slot_id);
+
+        SlotReader {
+            file,
+            bridge_slot_id,
+            size,
+            buffer: SlotBuffer([0; N]),
+            buffer_offset: 0,
+            position: 0,
+            filled: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> &'a mut File<B> {
+        self.file
+    }
+
+    pub fn bridge_slot_id(&self) -> u32 {
+        self.bridge_slot_id
+    }
+
+    ///
+    /// The size of the file in the slot, as of when the reader was created
+    ///
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    ///
+    /// The current read offset into the file
+    ///
+    pub fn position(&self) -> u32 {
+        self.buffer_offset + self.position as u32
+    }
+
+    fn discard_buffer(&mut self, offset: u32) {
+        self.buffer_offset = offset;
+        self.position = 0;
+        self.filled = 0;
+    }
+}
+
+impl embedded_io::Error for ReadError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ReadError::OffsetPastEnd | ReadError::InvalidSeek => ErrorKind::InvalidInput,
+            ReadError::BufferOutsideRam => ErrorKind::InvalidData,
+            ReadError::Bridge(_) => ErrorKind::Other,
+        }
+    }
+}
+
+impl<'a, const N: usize, B: BridgeBackend> ErrorType for SlotReader<'a, N, B> {
+    type Error = ReadError;
+}
+
+impl<'a, const N: usize, B: BridgeBackend> Read for SlotReader<'a, N, B> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.position == self.filled && buf.len() >= N {
+            // Nothing is buffered and the request is large, so skip the copy
+            let offset = self.position();
+
+            let count = if offset < self.size {
+                self.file.read_into(self.bridge_slot_id, offset, buf)?
+            } else {
+                0
+            };
+
+            self.discard_buffer(offset + count as u32);
+
+            return Ok(count);
+        }
+
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+
+        Ok(count)
+    }
+}
+
+impl<'a, const N: usize, B: BridgeBackend> BufRead for SlotReader<'a, N, B> {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        if self.position == self.filled {
+            let offset = self.position();
+
+            self.discard_buffer(offset);
+
+            if offset < self.size {
+                self.filled =
+                    self.file
+                        .read_into(self.bridge_slot_id, offset, &mut self.buffer.0)?;
+            }
+        }
+
+        Ok(&self.buffer.0[self.position..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position = (self.position + amt).min(self.filled);
+    }
+}
+
+impl<'a, const N: usize, B: BridgeBackend> Seek for SlotReader<'a, N, B> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => (self.size as u64).checked_add_signed(delta),
+            SeekFrom::Current(delta) => (self.position() as u64).checked_add_signed(delta),
+        }
+        .filter(|target| *target <= u32::MAX as u64)
+        .ok_or(ReadError::InvalidSeek)? as u32;
+
+        let buffer_end = self.buffer_offset + self.filled as u32;
+
+        if target >= self.buffer_offset && target <= buffer_end {
+            // Still within the buffered data, so keep it
+            self.position = (target - self.buffer_offset) as usize;
+        } else {
+            self.discard_buffer(target);
+        }
+
+        Ok(target as u64)
+    }
+}
+
+impl<B: BridgeBackend> File<B> {
+    ///
+    /// Creates a buffered reader over slot `bridge_slot_id`
+    ///
+    pub fn slot_reader(
+        &mut self,
+        bridge_slot_id: u32,
+    ) -> SlotReader<'_, DEFAULT_SLOT_BUFFER_SIZE, B> {
+        SlotReader::new(self, bridge_slot_id)
+    }
+}
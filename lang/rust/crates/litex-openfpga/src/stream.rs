@@ -0,0 +1,378 @@
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use litex_pac as pac;
+
+use crate::{
+    Audio, BridgeBackend, BridgeError, File, Resampler, StereoSample, WavError, WavFormat,
+    DEFAULT_TARGET_FILL,
+};
+
+/// The default size of each `StreamBuffers` chunk, in bytes. About 21ms of 48kHz stereo audio
+pub const DEFAULT_STREAM_CHUNK_SIZE: usize = 4096;
+
+// Word aligned so the bridge can transfer directly into the chunk
+#[repr(align(4))]
+struct Chunk<const N: usize>([u8; N]);
+
+/// The pair of RAM chunks a `StreamPlayer` streams through. `N` must be a multiple of 4.
+///
+/// The bridge writes into these in the background, so they are borrowed separately from the player, and stay put for
+/// as long as it exists.
+pub struct StreamBuffers<const N: usize = DEFAULT_STREAM_CHUNK_SIZE> {
+    chunks: [Chunk<N>; 2],
+}
+
+impl<const N: usize> StreamBuffers<N> {
+    pub const fn new() -> Self {
+        StreamBuffers {
+            chunks: [Chunk([0; N]), Chunk([0; N])],
+        }
+    }
+}
+
+impl<const N: usize> Default for StreamBuffers<N> {
+    fn default() -> Self {
+        StreamBuffers::new()
+    }
+}
+
+/// Gap free playback of a WAV file too large to load, streamed from a bridge slot.
+///
+/// One chunk is decoded while the bridge reads the next file data into the other. `update` never blocks: it collects
+/// finished reads, tops up the audio FIFO, and starts the next read as soon as a chunk has been consumed.
+///
+/// The player borrows the `File` for as long as it exists, as the bridge can only perform one operation at a time. Use
+/// `file` to reach it between reads. Dropping the player waits for any read still writing into the buffers.
+pub struct StreamPlayer<
+    'a,
+    const N: usize = DEFAULT_STREAM_CHUNK_SIZE,
+    B: BridgeBackend = pac::APF_BRIDGE,
+> {
+    file: &'a mut File<B>,
+    buffers: &'a mut StreamBuffers<N>,
+    bridge_slot_id: u32,
+    resampler: Resampler,
+    // Valid bytes in each chunk. 0 if the chunk is empty, or being read into
+    lengths: [usize; 2],
+    // The chunk being decoded, and the read offset into it
+    front: usize,
+    consumed: usize,
+    // The chunk the bridge is reading into, and the length of the read
+    reading: Option<(usize, usize)>,
+    // Offset of the next read from the start of the sample data
+    next_offset: u32,
+    looping: bool,
+    playing: bool,
+    // Whether a chunk has arrived since the start of the stream. Until then, running dry isn't starvation
+    primed: bool,
+    target_fill: usize,
+    starved: u32,
+}
+
+impl<'a, const N: usize, B: BridgeBackend> StreamPlayer<'a, N, B> {
+    ///
+    /// Reads the WAV header from slot `bridge_slot_id`, blocking until complete. Playback starts paused
+    ///
+    pub fn open(
+        file: &'a mut File<B>,
+        bridge_slot_id: u32,
+        buffers: &'a mut StreamBuffers<N>,
+    ) -> Result<Self, WavError> {
+        let format = WavFormat::parse(&mut file.slot_reader(bridge_slot_id))?;
+
+        Ok(StreamPlayer::with_format(
+            file,
+            buffers,
+            bridge_slot_id,
+            format,
+        ))
+    }
+
+    ///
+    /// Streams sample data described by `format`, such as headerless PCM. Playback starts paused, with the first chunk
+    /// already being read
+    ///
+    pub fn with_format(
+        file: &'a mut File<B>,
+        buffers: &'a mut StreamBuffers<N>,
+        bridge_slot_id: u32,
+        format: WavFormat,
+    ) -> Self {
+        assert!(N >= 4 && N & 0x3 == 0, "stream chunks must be whole words");
+
+        let mut player = StreamPlayer {
+            file,
+            buffers,
+            bridge_slot_id,
+            resampler: Resampler::new(format),
+            lengths: [0; 2],
+            front: 0,
+            consumed: 0,
+            reading: None,
+            next_offset: 0,
+            looping: false,
+            playing: false,
+            primed: false,
+            target_fill: DEFAULT_TARGET_FILL,
+            starved: 0,
+        };
+
+        player.start_read();
+        player
+    }
+
+    ///
+    /// Waits for any in flight read, then lends out the `File` for other operations
+    ///
+    pub fn file(&mut self) -> Result<&mut File<B>, BridgeError> {
+        self.wait_for_read()?;
+
+        Ok(self.file)
+    }
+
+    pub fn format(&self) -> &WavFormat {
+        self.resampler.format()
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    ///
+    /// True once a non looping stream has played to the end
+    ///
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.resampler.is_finished()
+    }
+
+    ///
+    /// True while the bridge is reading the next chunk
+    ///
+    pub fn is_reading(&self) -> bool {
+        self.reading.is_some()
+    }
+
+    ///
+    /// The number of times the FIFO needed samples, but the bridge hadn't delivered them yet. Waiting for the first
+    /// chunk after opening or `stop` doesn't count
+    ///
+    pub fn starved(&self) -> u32 {
+        self.starved
+    }
+
+    pub fn target_fill(&self) -> usize {
+        self.target_fill
+    }
+
+    ///
+    /// Sets the number of samples `update` keeps queued in the FIFO, up to one less than its capacity
+    ///
+    pub fn set_target_fill(&mut self, samples: usize) {
+        self.target_fill = samples;
+    }
+
+    ///
+    /// Services the bridge and tops up the audio FIFO, returning the number of samples pushed. Call once per frame
+    ///
+    pub fn update(&mut self, audio: &mut Audio) -> Result<usize, BridgeError> {
+        self.pump()?;
+
+        let pushed = if self.playing { self.fill(audio) } else { 0 };
+
+        // Decoding may have freed a chunk
+        self.pump()?;
+
+        Ok(pushed)
+    }
+
+    ///
+    /// Collects a finished bridge read, and starts the next one if a chunk is free. Never blocks
+    ///
+    pub fn pump(&mut self) -> Result<(), BridgeError> {
+        if self.reading.is_some() {
+            if !self.file.check_op_complete() {
+                return Ok(());
+            }
+
+            self.complete_read()?;
+        }
+
+        self.start_read();
+
+        Ok(())
+    }
+
+    ///
+    /// Blocks until any in flight bridge read finishes, leaving the bridge free for other operations
+    ///
+    pub fn wait_for_read(&mut self) -> Result<(), BridgeError> {
+        if self.reading.is_some() {
+            self.file.block_op_complete();
+
+            self.complete_read()?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Stops playback and returns to the start of the stream
+    ///
+    pub fn stop(&mut self) -> Result<(), BridgeError> {
+        let result = self.wait_for_read();
+
+        self.playing = false;
+        self.primed = false;
+        self.lengths = [0; 2];
+        self.front = 0;
+        self.consumed = 0;
+        self.next_offset = 0;
+        self.resampler.rewind();
+
+        result
+    }
+
+    ///
+    /// Decodes already buffered audio into `output`, returning the number of samples written. This doesn't touch the
+    /// bridge or the FIFO, so together with `pump`, a stream can be mixed with other audio. Counts as starved if
+    /// playing, but nothing has been delivered to decode
+    ///
+    pub fn decode(&mut self, output: &mut [StereoSample]) -> usize {
+        if self.format().frames() == 0 {
+            return 0;
+        }
+
+        let mut written = 0;
+
+        while written < output.len() {
+            if self.resampler.is_finished() {
+                if !self.looping {
+                    self.playing = false;
+                    break;
+                }
+
+                // Reads have already wrapped around to the start of the data
+                self.resampler.rewind();
+            }
+
+            let front = self.front;
+            let bytes = &self.buffers.chunks[front].0[self.consumed..self.lengths[front]];
+            let (count, consumed) = self.resampler.resample_bytes(&mut output[written..], bytes);
+
+            written += count;
+            self.consumed += consumed;
+
+            if written == output.len() || self.resampler.is_finished() {
+                continue;
+            }
+
+            // The front chunk has run dry
+            if self.lengths[front] == 0 {
+                break;
+            }
+
+            self.lengths[front] = 0;
+            self.consumed = 0;
+            self.front ^= 1;
+
+            if self.lengths[self.front] == 0 {
+                // The bridge hasn't delivered the next chunk yet
+                break;
+            }
+        }
+
+        if written == 0 && !output.is_empty() && self.playing && self.primed {
+            self.starved = self.starved.wrapping_add(1);
+        }
+
+        written
+    }
+
+    fn fill(&mut self, audio: &mut Audio) -> usize {
+        audio.fill_to(self.target_fill, |chunk| self.decode(chunk))
+    }
+
+    fn start_read(&mut self) {
+        if self.reading.is_some() {
+            return;
+        }
+
+        // Data must be consumed in the order it was read, so fill the front chunk only if both are empty
+        let chunk = if self.lengths[self.front] == 0 {
+            self.front
+        } else {
+            self.front ^ 1
+        };
+
+        if self.lengths[chunk] != 0 {
+            return;
+        }
+
+        let format = *self.resampler.format();
+        // Ignore any partial frame at the end
+        let data_length = format.frames() * format.frame_size();
+
+        if self.next_offset >= data_length {
+            if !self.looping || data_length == 0 {
+                return;
+            }
+
+            self.next_offset = 0;
+        }
+
+        let length = (data_length - self.next_offset).min(N as u32);
+        let address = self.buffers.chunks[chunk].0.as_mut_ptr() as usize;
+
+        // The transfer can't be held across frames, so completion is collected by `pump`, or `drop` at the latest
+        drop(self.file.request_read(
+            format.data_offset + self.next_offset,
+            length,
+            address,
+            self.bridge_slot_id,
+        ));
+
+        self.next_offset += length;
+        self.reading = Some((chunk, length as usize));
+    }
+
+    fn complete_read(&mut self) -> Result<(), BridgeError> {
+        let Some((chunk, length)) = self.reading.take() else {
+            return Ok(());
+        };
+
+        // The bridge wrote behind the compiler's back
+        compiler_fence(Ordering::SeqCst);
+
+        BridgeError::from_result_code(self.file.command_result_code())?;
+
+        self.lengths[chunk] = length;
+        self.primed = true;
+
+        Ok(())
+    }
+}
+
+impl<const N: usize, B: BridgeBackend> Drop for StreamPlayer<'_, N, B> {
+    fn drop(&mut self) {
+        // The bridge may still be writing into the buffers, which are about to be released
+        if self.reading.is_some() {
+            self.file.block_op_complete();
+        }
+    }
+}
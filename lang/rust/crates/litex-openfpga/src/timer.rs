@@ -0,0 +1,55 @@
+use core::time::Duration;
+use litex_pac as pac;
+
+/// Owned access to `TIMER0`, used as the monotonic uptime source for the SoC.
+pub struct Timer {
+    registers: pac::TIMER0,
+    clock_speed_hz: u32,
+}
+
+fn combine_u32(low: u32, high: u32) -> u64 {
+    ((high as u64) << 32) | (low as u64)
+}
+
+impl Timer {
+    pub fn new(registers: pac::TIMER0, clock_speed_hz: u32) -> Self {
+        Timer {
+            registers,
+            clock_speed_hz,
+        }
+    }
+
+    pub fn free(self) -> pac::TIMER0 {
+        self.registers
+    }
+
+    pub fn clock_speed_hz(&self) -> u32 {
+        self.clock_speed_hz
+    }
+
+    ///
+    /// Latches and returns the number of CPU cycles since startup
+    ///
+    pub fn uptime_cycles(&self) -> u64 {
+        unsafe {
+            // Grab cycle count
+            self.registers.uptime_latch.write(|w| w.bits(1));
+        };
+
+        let low_bits = self.registers.uptime_cycles0.read().bits();
+        let high_bits = self.registers.uptime_cycles1.read().bits();
+
+        combine_u32(low_bits, high_bits)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        let uptime_cycles = self.uptime_cycles();
+        let clock_speed_hz = self.clock_speed_hz as u64;
+
+        let seconds = uptime_cycles / clock_speed_hz;
+        // Remainder is less than `clock_speed_hz`, so this can't overflow
+        let nanos = (uptime_cycles % clock_speed_hz) * 1_000_000_000 / clock_speed_hz;
+
+        Duration::new(seconds, nanos as u32)
+    }
+}
@@ -1,35 +1,57 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use critical_section::Mutex;
 use litex_hal;
 
 litex_hal::uart! {
     UART: crate::litex_pac::UART,
 }
 
-#[macro_export]
-macro_rules! println {
-    ($($arg:tt)*) => {{
-        {
-            use core::fmt::Write;
-            // Hopefully this is zero cost
-            let peripherals = unsafe { crate::litex_pac::Peripherals::steal() };
+static PRINTER: Mutex<RefCell<Option<UART>>> = Mutex::new(RefCell::new(None));
+
+///
+/// Hands ownership of the UART to `print!` and `println!`. Until this is called, printing does nothing
+///
+pub fn set_printer(uart: UART) {
+    critical_section::with(|cs| PRINTER.borrow_ref_mut(cs).replace(uart));
+}
+
+///
+/// Takes the UART back from `print!` and `println!`, if it was set
+///
+pub fn take_printer() -> Option<UART> {
+    critical_section::with(|cs| PRINTER.borrow_ref_mut(cs).take())
+}
 
-            let mut serial = UART::new(peripherals.UART);
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments, newline: bool) {
+    critical_section::with(|cs| {
+        // Use `try_` so a panic while printing doesn't panic again in the panic handler
+        if let Ok(mut printer) = PRINTER.borrow(cs).try_borrow_mut() {
+            if let Some(serial) = printer.as_mut() {
+                serial.write_fmt(args).ok();
 
-            writeln!(serial, $($arg)*).ok();
+                if newline {
+                    serial.write_char('\n').ok();
+                }
+            }
         }
+    });
+}
+
+#[macro_export]
+macro_rules! println {
+    () => {{
+        $crate::uart_printer::_print(format_args!(""), true);
+    }};
+    ($($arg:tt)*) => {{
+        $crate::uart_printer::_print(format_args!($($arg)*), true);
     }};
 }
 
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => {{
-        {
-            use core::fmt::Write;
-            // Hopefully this is zero cost
-            let peripherals = unsafe { crate::litex_pac::Peripherals::steal() };
-
-            let mut serial = UART::new(peripherals.UART);
-
-            write!(serial, $($arg)*).ok();
-        }
+        $crate::uart_printer::_print(format_args!($($arg)*), false);
     }};
 }
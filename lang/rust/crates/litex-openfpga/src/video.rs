@@ -0,0 +1,365 @@
+use core::{ops::Range, ptr::copy_nonoverlapping, slice::from_raw_parts_mut};
+
+use litex_pac as pac;
+use pac::constants;
+
+use crate::Canvas;
+
+/// The width of the display, in pixels
+pub const FRAMEBUFFER_WIDTH: usize = constants::VIDEO_FRAMEBUFFER_HRES as usize;
+/// The height of the display, in pixels
+pub const FRAMEBUFFER_HEIGHT: usize = constants::VIDEO_FRAMEBUFFER_VRES as usize;
+/// The number of pixels in one framebuffer
+pub const FRAMEBUFFER_PIXELS: usize = FRAMEBUFFER_WIDTH * FRAMEBUFFER_HEIGHT;
+
+/// The size of the `video_framebuffer` memory region, starting at `VIDEO_FRAMEBUFFER_BASE`
+pub const FRAMEBUFFER_REGION_SIZE: usize = 0x0080_0000;
+
+const FRAMEBUFFER_BYTES: usize = FRAMEBUFFER_PIXELS * 2;
+
+/// The bits of `VideoStatus::frame_counter`, which is 30 bits wide
+pub const FRAME_COUNTER_MASK: u32 = 0x3FFF_FFFF;
+
+///
+/// The frames displayed between two readings of `VideoStatus::frame_counter`, allowing for wrap around. 1 if there is
+/// no `last` reading, and 0 if both are from the same frame
+///
+pub const fn frames_since(last: Option<u32>, now: u32) -> u32 {
+    match last {
+        Some(last) => now.wrapping_sub(last) & FRAME_COUNTER_MASK,
+        None => 1,
+    }
+}
+
+/// A single snapshot of the `APF_VIDEO` register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoStatus {
+    /// True while the display is in vblank
+    pub vblank: bool,
+    /// True if vblank has started since the last read of the register
+    pub vblank_triggered: bool,
+    /// Number of frames displayed since startup. This is a 30 bit counter, see `FRAME_COUNTER_MASK`
+    pub frame_counter: u32,
+}
+
+/// Owned access to the video blocks: `APF_VIDEO`, `VIDEO_FRAMEBUFFER`, and `VIDEO_FRAMEBUFFER_VTG`.
+pub struct Video {
+    video: pac::APF_VIDEO,
+    framebuffer: pac::VIDEO_FRAMEBUFFER,
+    vtg: pac::VIDEO_FRAMEBUFFER_VTG,
+}
+
+impl Video {
+    pub fn new(
+        video: pac::APF_VIDEO,
+        framebuffer: pac::VIDEO_FRAMEBUFFER,
+        vtg: pac::VIDEO_FRAMEBUFFER_VTG,
+    ) -> Self {
+        Video {
+            video,
+            framebuffer,
+            vtg,
+        }
+    }
+
+    pub fn free(
+        self,
+    ) -> (
+        pac::APF_VIDEO,
+        pac::VIDEO_FRAMEBUFFER,
+        pac::VIDEO_FRAMEBUFFER_VTG,
+    ) {
+        (self.video, self.framebuffer, self.vtg)
+    }
+
+    ///
+    /// Reads the video status register. Note that this clears `vblank_triggered` in hardware
+    ///
+    pub fn status(&self) -> VideoStatus {
+        let video_data = self.video.video.read();
+
+        VideoStatus {
+            vblank: video_data.vblank_status().bit(),
+            vblank_triggered: video_data.vblank_triggered().bit(),
+            frame_counter: video_data.frame_counter().bits(),
+        }
+    }
+
+    ///
+    /// The address the framebuffer DMA is currently reading from
+    ///
+    pub fn framebuffer_address(&self) -> u32 {
+        self.framebuffer.dma_base.read().bits()
+    }
+
+    ///
+    /// Points the framebuffer DMA at `address` without halting video. Only tear free during vblank
+    ///
+    pub fn swap_framebuffer_address(&mut self, address: u32) {
+        unsafe {
+            self.framebuffer.dma_base.write(|w| w.bits(address));
+        }
+    }
+
+    ///
+    /// Points the framebuffer DMA at `address`. Video output is briefly halted while the DMA restarts
+    ///
+    pub fn set_framebuffer_address(&mut self, address: u32) {
+        unsafe {
+            // FB Off
+            self.vtg.enable.write(|w| w.bits(0));
+            self.framebuffer.dma_enable.write(|w| w.bits(0));
+
+            // Set read page
+            self.framebuffer.dma_offset.write(|w| w.bits(0));
+            self.framebuffer.dma_base.write(|w| w.bits(address));
+
+            // FB On
+            self.vtg.enable.write(|w| w.bits(1));
+            self.framebuffer.dma_enable.write(|w| w.bits(1));
+        }
+    }
+}
+
+/// The register level operations `Framebuffers` is built on.
+///
+/// `Video` implements this for the real hardware. Anything else that implements it can stand in for the display, so
+/// buffer flipping can be tested off device.
+pub trait VideoBackend {
+    ///
+    /// Reads the video status. `vblank_triggered` is cleared on read
+    ///
+    fn status(&self) -> VideoStatus;
+
+    ///
+    /// Points the framebuffer DMA at `address` without halting video
+    ///
+    fn swap_framebuffer_address(&mut self, address: u32);
+
+    ///
+    /// Points the framebuffer DMA at `address`, restarting it
+    ///
+    fn set_framebuffer_address(&mut self, address: u32);
+}
+
+impl VideoBackend for Video {
+    fn status(&self) -> VideoStatus {
+        Video::status(self)
+    }
+
+    fn swap_framebuffer_address(&mut self, address: u32) {
+        Video::swap_framebuffer_address(self, address);
+    }
+
+    fn set_framebuffer_address(&mut self, address: u32) {
+        Video::set_framebuffer_address(self, address);
+    }
+}
+
+/// A 16 bit RGB565 pixel, as read by the framebuffer DMA.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rgb565(pub u16);
+
+impl Rgb565 {
+    pub const BLACK: Rgb565 = Rgb565(0x0000);
+    pub const WHITE: Rgb565 = Rgb565(0xFFFF);
+
+    ///
+    /// Converts 8 bit color channels, dropping the low bits
+    ///
+    pub const fn from_rgb888(red: u8, green: u8, blue: u8) -> Self {
+        Rgb565(((red as u16 & 0xF8) << 8) | ((green as u16 & 0xFC) << 3) | (blue as u16 >> 3))
+    }
+
+    ///
+    /// Expands to 8 bit color channels, so full intensity stays full
+    ///
+    pub const fn to_rgb888(self) -> (u8, u8, u8) {
+        let red = (self.0 >> 11) as u8 & 0x1F;
+        let green = (self.0 >> 5) as u8 & 0x3F;
+        let blue = self.0 as u8 & 0x1F;
+
+        (
+            (red << 3) | (red >> 2),
+            (green << 2) | (green >> 4),
+            (blue << 3) | (blue >> 2),
+        )
+    }
+}
+
+impl From<Rgb565> for u16 {
+    fn from(color: Rgb565) -> Self {
+        color.0
+    }
+}
+
+/// `N` RGB565 framebuffers in the `video_framebuffer` region, flipped at vblank so the display never tears.
+///
+/// Draw into `back_buffer`, then call `present`. With 2 buffers, `present` waits for vblank and flips immediately.
+/// With 3 or more, `present` queues the frame and returns as soon as a buffer is free to draw the next one into; the
+/// queued frame is flipped at the next vblank seen by `present` or `poll`. Owns `Video`, so nothing else moves the
+/// framebuffer DMA.
+pub struct Framebuffers<const N: usize, V: VideoBackend = Video> {
+    video: V,
+    displayed: usize,
+    pending: Option<usize>,
+    back: usize,
+    frame_counter: u32,
+    last_flip_frame: Option<u32>,
+}
+
+impl<const N: usize, V: VideoBackend> Framebuffers<N, V> {
+    ///
+    /// Displays buffer 0 and starts drawing into buffer 1. The buffers are not cleared
+    ///
+    pub fn new(mut video: V) -> Self {
+        assert!(
+            N >= 2 && N * FRAMEBUFFER_BYTES <= FRAMEBUFFER_REGION_SIZE,
+            "Framebuffers needs at least 2 buffers, which must fit in the video_framebuffer region"
+        );
+
+        video.set_framebuffer_address(Self::buffer_address(0));
+
+        Framebuffers {
+            frame_counter: video.status().frame_counter,
+            video,
+            displayed: 0,
+            pending: None,
+            back: 1,
+            last_flip_frame: None,
+        }
+    }
+
+    pub fn free(self) -> V {
+        self.video
+    }
+
+    pub fn video(&self) -> &V {
+        &self.video
+    }
+
+    ///
+    /// The address of buffer `index`, from 0 to `N`
+    ///
+    pub const fn buffer_address(index: usize) -> u32 {
+        constants::VIDEO_FRAMEBUFFER_BASE + (index * FRAMEBUFFER_BYTES) as u32
+    }
+
+    ///
+    /// The index of the buffer being displayed
+    ///
+    pub fn front_index(&self) -> usize {
+        self.displayed
+    }
+
+    ///
+    /// The index of the buffer being drawn into
+    ///
+    pub fn back_index(&self) -> usize {
+        self.back
+    }
+
+    ///
+    /// The buffer to draw the next frame into, `FRAMEBUFFER_WIDTH` pixels per row. Its contents are whatever was drawn
+    /// into it `N` frames ago
+    ///
+    pub fn back_buffer(&mut self) -> &mut [Rgb565] {
+        // Owning `Video` makes us the only user of the region, and the back buffer is never being displayed
+        unsafe {
+            from_raw_parts_mut(
+                Self::buffer_address(self.back) as *mut Rgb565,
+                FRAMEBUFFER_PIXELS,
+            )
+        }
+    }
+
+    ///
+    /// A `Canvas` for drawing into the back buffer
+    ///
+    pub fn canvas(&mut self) -> Canvas<'_> {
+        Canvas::new(self.back_buffer(), FRAMEBUFFER_WIDTH, FRAMEBUFFER_HEIGHT)
+    }
+
+    ///
+    /// Copies `rows` of buffer `source` into the back buffer, to bring it up to date with a frame drawn elsewhere
+    ///
+    pub fn copy_rows_to_back(&mut self, source: usize, rows: Range<usize>) {
+        assert!(source < N && rows.end <= FRAMEBUFFER_HEIGHT);
+
+        if source == self.back || rows.is_empty() {
+            return;
+        }
+
+        let start = rows.start * FRAMEBUFFER_WIDTH;
+        let count = rows.len() * FRAMEBUFFER_WIDTH;
+
+        // Buffers never overlap, and the source is only ever read by the display
+        unsafe {
+            copy_nonoverlapping(
+                (Self::buffer_address(source) as *const Rgb565).add(start),
+                (Self::buffer_address(self.back) as *mut Rgb565).add(start),
+                count,
+            );
+        }
+    }
+
+    ///
+    /// Queues the back buffer for display, and moves on to the next buffer. Returns the `VideoStatus::frame_counter`
+    /// of the last vblank seen, suitable for `InputState::update`
+    ///
+    pub fn present(&mut self) -> u32 {
+        // Only one frame may be queued. With 2 buffers, this also waits for our own frame to be displayed
+        while self.pending.is_some() {
+            self.poll();
+        }
+
+        self.pending = Some(self.back);
+
+        // Take the buffers in turn, so each is drawn into every `N` frames
+        let next = (1..N)
+            .map(|step| (self.back + step) % N)
+            .find(|&index| index != self.displayed);
+
+        match next {
+            Some(index) => self.back = index,
+            None => {
+                while self.pending.is_some() {
+                    self.poll();
+                }
+
+                // The previously displayed buffer is now free
+                self.back = (self.back + 1) % N;
+            }
+        }
+
+        self.frame_counter
+    }
+
+    ///
+    /// Flips to the queued frame if vblank has started. Returns true if the display changed. Call while drawing a
+    /// slow frame, so a queued frame isn't held back until the next `present`
+    ///
+    pub fn poll(&mut self) -> bool {
+        let status = self.video.status();
+
+        if !(status.vblank_triggered
+            || (status.vblank && self.last_flip_frame != Some(status.frame_counter)))
+        {
+            return false;
+        }
+
+        self.frame_counter = status.frame_counter;
+
+        let Some(pending) = self.pending.take() else {
+            return false;
+        };
+
+        self.video
+            .swap_framebuffer_address(Self::buffer_address(pending));
+        self.displayed = pending;
+        self.last_flip_frame = Some(status.frame_counter);
+
+        true
+    }
+}
@@ -0,0 +1,409 @@
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use embedded_io::{Read, ReadExactError, Seek, SeekFrom};
+
+use crate::{BridgeBackend, File, ReadError, StereoSample, SAMPLE_RATE};
+
+// `WAVE_FORMAT_PCM` and `WAVE_FORMAT_EXTENSIBLE`
+const FORMAT_PCM: u16 = 0x0001;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// A failed WAV decode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WavError<E = ReadError> {
+    /// The file isn't a RIFF/WAVE file
+    NotWav,
+    /// The file isn't 8 or 16 bit PCM with one or two channels
+    UnsupportedFormat,
+    /// The file has no `fmt ` or `data` chunk, or ends early
+    Truncated,
+    /// The underlying reader failed
+    Read(E),
+}
+
+impl<E> From<E> for WavError<E> {
+    fn from(error: E) -> Self {
+        WavError::Read(error)
+    }
+}
+
+impl<E> From<ReadExactError<E>> for WavError<E> {
+    fn from(error: ReadExactError<E>) -> Self {
+        match error {
+            ReadExactError::UnexpectedEof => WavError::Truncated,
+            ReadExactError::Other(error) => WavError::Read(error),
+        }
+    }
+}
+
+/// The layout of a WAV file, from its `fmt ` and `data` chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WavFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    /// Offset of the sample data from the start of the file
+    pub data_offset: u32,
+    /// Length of the sample data, in bytes
+    pub data_length: u32,
+}
+
+impl WavFormat {
+    ///
+    /// Reads the format from a WAV header, leaving `reader` at an unspecified position
+    ///
+    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<WavFormat, WavError<R::Error>> {
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff)?;
+
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            return Err(WavError::NotWav);
+        }
+
+        let file_length = reader.seek(SeekFrom::End(0))?;
+        let mut offset = reader.seek(SeekFrom::Start(12))?;
+
+        let mut format: Option<(u16, u32, u16)> = None;
+        let mut data: Option<(u32, u32)> = None;
+
+        while format.is_none() || data.is_none() {
+            let mut header = [0u8; 8];
+            reader.read_exact(&mut header)?;
+
+            let id = &header[0..4];
+            let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            let body = offset + 8;
+
+            match id {
+                b"fmt " => {
+                    let mut fmt = [0u8; 26];
+                    let fmt = &mut fmt[..(length as usize).min(26)];
+
+                    if fmt.len() < 16 {
+                        return Err(WavError::UnsupportedFormat);
+                    }
+
+                    reader.read_exact(fmt)?;
+
+                    let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                    let bits_per_sample = u16::from_le_bytes([fmt[14], fmt[15]]);
+
+                    // Extensible files carry the real format tag at the start of the subformat GUID
+                    let tag = match tag {
+                        FORMAT_EXTENSIBLE if fmt.len() >= 26 => {
+                            u16::from_le_bytes([fmt[24], fmt[25]])
+                        }
+                        tag => tag,
+                    };
+
+                    if tag != FORMAT_PCM
+                        || !(1..=2).contains(&channels)
+                        || !(bits_per_sample == 8 || bits_per_sample == 16)
+                        || sample_rate == 0
+                    {
+                        return Err(WavError::UnsupportedFormat);
+                    }
+
+                    format = Some((channels, sample_rate, bits_per_sample));
+                }
+                b"data" => {
+                    // Streaming writers may leave the length unset, so never read past the end of the file
+                    let length = (length as u64).min(file_length.saturating_sub(body));
+
+                    data = Some((body as u32, length as u32));
+                }
+                _ => {}
+            }
+
+            // Chunks are padded to an even length
+            offset = body + length as u64 + (length & 1) as u64;
+
+            if offset >= file_length {
+                break;
+            }
+
+            reader.seek(SeekFrom::Start(offset))?;
+        }
+
+        match (format, data) {
+            (Some((channels, sample_rate, bits_per_sample)), Some((data_offset, data_length))) => {
+                Ok(WavFormat {
+                    channels,
+                    sample_rate,
+                    bits_per_sample,
+                    data_offset,
+                    data_length,
+                })
+            }
+            _ => Err(WavError::Truncated),
+        }
+    }
+
+    ///
+    /// The size of one sample for every channel, in bytes
+    ///
+    pub fn frame_size(&self) -> u32 {
+        self.channels as u32 * (self.bits_per_sample as u32 / 8)
+    }
+
+    ///
+    /// The number of frames in the file, at the file's sample rate
+    ///
+    pub fn frames(&self) -> u32 {
+        self.data_length / self.frame_size()
+    }
+
+    ///
+    /// The number of samples the file decodes to once resampled to `SAMPLE_RATE`
+    ///
+    pub fn output_length(&self) -> usize {
+        let end = (self.frames() as u64) << 32;
+        let step = self.step();
+
+        end.div_ceil(step) as usize
+    }
+
+    // Source frames advanced per output sample, in 32.32 fixed point. Rounded up, so rounding error can't add a
+    // sample to the end of the output
+    fn step(&self) -> u64 {
+        ((self.sample_rate as u64) << 32).div_ceil(SAMPLE_RATE as u64)
+    }
+
+    ///
+    /// Converts one frame of raw sample data, `frame_size` bytes long
+    ///
+    pub(crate) fn decode_frame(&self, bytes: &[u8]) -> StereoSample {
+        let sample = |index: usize| match self.bits_per_sample {
+            // 8 bit samples are unsigned
+            8 => ((bytes[index] as i16) - 128) << 8,
+            _ => i16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]]),
+        };
+
+        match self.channels {
+            1 => StereoSample::mono(sample(0)),
+            _ => StereoSample::new(sample(0), sample(1)),
+        }
+    }
+}
+
+/// A streaming WAV decoder, producing `StereoSample`s at `SAMPLE_RATE`.
+///
+/// Supports 8 and 16 bit PCM, mono or stereo, at any sample rate. Mono is copied to both channels, and other sample
+/// rates are linearly interpolated. Works over any `embedded_io` reader, though usually a `SlotReader`.
+pub struct WavDecoder<R> {
+    reader: R,
+    resampler: Resampler,
+}
+
+impl<R: Read + Seek> WavDecoder<R> {
+    ///
+    /// Parses the WAV header and prepares to decode from the first sample
+    ///
+    pub fn new(mut reader: R) -> Result<Self, WavError<R::Error>> {
+        let format = WavFormat::parse(&mut reader)?;
+
+        let mut decoder = WavDecoder {
+            reader,
+            resampler: Resampler::new(format),
+        };
+
+        decoder.rewind()?;
+
+        Ok(decoder)
+    }
+
+    pub fn format(&self) -> &WavFormat {
+        &self.resampler.format
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    ///
+    /// True once every sample has been decoded
+    ///
+    pub fn is_finished(&self) -> bool {
+        self.resampler.is_finished()
+    }
+
+    ///
+    /// Restarts decoding from the first sample
+    ///
+    pub fn rewind(&mut self) -> Result<(), WavError<R::Error>> {
+        self.reader
+            .seek(SeekFrom::Start(self.format().data_offset as u64))?;
+        self.resampler.rewind();
+
+        Ok(())
+    }
+
+    ///
+    /// Decodes into `output`, returning the number of samples written. Returns 0 once the file is finished
+    ///
+    pub fn decode(&mut self, output: &mut [StereoSample]) -> Result<usize, WavError<R::Error>> {
+        let format = self.resampler.format;
+        let reader = &mut self.reader;
+
+        self.resampler.resample(output, || {
+            let mut bytes = [0u8; 4];
+            let bytes = &mut bytes[..format.frame_size() as usize];
+
+            reader.read_exact(bytes)?;
+
+            Ok(Some(format.decode_frame(bytes)))
+        })
+    }
+}
+
+/// Linear interpolation of a file's frames up or down to `SAMPLE_RATE`. Frames are pulled in order as needed, so
+/// decoding can pause whenever the source runs dry and resume later.
+pub(crate) struct Resampler {
+    format: WavFormat,
+    // Position in source frames, in 32.32 fixed point
+    position: u64,
+    step: u64,
+    // The last two frames pulled, and the number pulled so far
+    current: StereoSample,
+    next: StereoSample,
+    loaded: u32,
+}
+
+impl Resampler {
+    pub(crate) fn new(format: WavFormat) -> Self {
+        Resampler {
+            format,
+            position: 0,
+            step: format.step(),
+            current: StereoSample::SILENCE,
+            next: StereoSample::SILENCE,
+            loaded: 0,
+        }
+    }
+
+    pub(crate) fn format(&self) -> &WavFormat {
+        &self.format
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        let frames = self.format.frames();
+
+        (self.position >> 32) >= frames as u64 && self.loaded >= frames
+    }
+
+    pub(crate) fn rewind(&mut self) {
+        self.position = 0;
+        self.loaded = 0;
+    }
+
+    ///
+    /// Resamples into `output`, returning the number of samples written. `next_frame` returns `None` when no more
+    /// frames are available yet, which stops early
+    ///
+    pub(crate) fn resample<E>(
+        &mut self,
+        output: &mut [StereoSample],
+        mut next_frame: impl FnMut() -> Result<Option<StereoSample>, E>,
+    ) -> Result<usize, E> {
+        let frames = self.format.frames() as u64;
+
+        for (count, sample) in output.iter_mut().enumerate() {
+            let index = self.position >> 32;
+
+            if index >= frames {
+                // Pull any trailing frames stepped over, so the source always ends at the end of the data
+                while (self.loaded as u64) < frames {
+                    if next_frame()?.is_none() {
+                        break;
+                    }
+
+                    self.loaded += 1;
+                }
+
+                return Ok(count);
+            }
+
+            // Interpolating needs the frame after `index`, unless it's the last
+            let needed = (index + 2).min(frames) as u32;
+
+            while self.loaded < needed {
+                let Some(frame) = next_frame()? else {
+                    return Ok(count);
+                };
+
+                if self.loaded == 0 {
+                    self.next = frame;
+                }
+
+                self.current = self.next;
+                self.next = frame;
+                self.loaded += 1;
+            }
+
+            *sample = if index + 2 == self.loaded as u64 {
+                let fraction = ((self.position >> 16) & 0xFFFF) as i32;
+
+                StereoSample::new(
+                    interpolate(self.current.left, self.next.left, fraction),
+                    interpolate(self.current.right, self.next.right, fraction),
+                )
+            } else {
+                // Hold the last frame
+                self.next
+            };
+
+            self.position += self.step;
+        }
+
+        Ok(output.len())
+    }
+
+    ///
+    /// Resamples from a buffer of raw sample data, returning the number of samples written and bytes consumed
+    ///
+    pub(crate) fn resample_bytes(
+        &mut self,
+        output: &mut [StereoSample],
+        bytes: &[u8],
+    ) -> (usize, usize) {
+        let format = self.format;
+        let frame_size = format.frame_size() as usize;
+        let mut frames = bytes.chunks_exact(frame_size);
+        let mut consumed = 0;
+
+        let count = self
+            .resample(output, || {
+                Ok::<_, Infallible>(frames.next().map(|frame| {
+                    consumed += frame_size;
+
+                    format.decode_frame(frame)
+                }))
+            })
+            .unwrap_or_else(|never| match never {});
+
+        (count, consumed)
+    }
+}
+
+fn interpolate(from: i16, to: i16, fraction: i32) -> i16 {
+    (from as i32 + (((to as i32 - from as i32) * fraction) >> 16)) as i16
+}
+
+impl<B: BridgeBackend> File<B> {
+    ///
+    /// Decodes the entire WAV file in slot `bridge_slot_id` into memory, resampled to `SAMPLE_RATE`
+    ///
+    pub fn load_wav(&mut self, bridge_slot_id: u32) -> Result<Vec<StereoSample>, WavError> {
+        let mut decoder = WavDecoder::new(self.slot_reader(bridge_slot_id))?;
+        let mut samples = Vec::new();
+
+        samples.resize(decoder.format().output_length(), StereoSample::SILENCE);
+
+        let length = decoder.decode(&mut samples)?;
+        samples.truncate(length);
+
+        Ok(samples)
+    }
+}
@@ -0,0 +1,98 @@
+// Fixtures shared by the test binaries. Each binary uses a different subset
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static DIRECTORIES: AtomicU32 = AtomicU32::new(0);
+
+///
+/// A new, empty directory for `MockBridge` slot files. Unique to this test and process, so tests can run in parallel
+///
+pub fn slot_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!(
+        "litex-openfpga-{}-{}-{name}",
+        std::process::id(),
+        DIRECTORIES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+///
+/// A RIFF chunk, padded to an even length
+///
+pub fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(body);
+
+    if body.len() % 2 == 1 {
+        bytes.push(0);
+    }
+
+    bytes
+}
+
+///
+/// A `fmt ` chunk. A `tag` of 0xFFFE writes `WAVE_FORMAT_EXTENSIBLE` with the PCM subformat
+///
+pub fn fmt(tag: u16, channels: u16, sample_rate: u32, bits_per_sample: u16) -> Vec<u8> {
+    let block_align = channels * bits_per_sample / 8;
+    let mut body = Vec::new();
+
+    body.extend_from_slice(&tag.to_le_bytes());
+    body.extend_from_slice(&channels.to_le_bytes());
+    body.extend_from_slice(&sample_rate.to_le_bytes());
+    body.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    body.extend_from_slice(&block_align.to_le_bytes());
+    body.extend_from_slice(&bits_per_sample.to_le_bytes());
+
+    if tag == 0xFFFE {
+        // Extension size, valid bits, channel mask, and the PCM subformat GUID
+        body.extend_from_slice(&22u16.to_le_bytes());
+        body.extend_from_slice(&bits_per_sample.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&[
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38,
+            0x9B, 0x71,
+        ]);
+    }
+
+    chunk(b"fmt ", &body)
+}
+
+///
+/// A WAV file of `chunks`
+///
+pub fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = chunks.concat();
+    let mut bytes = b"RIFF".to_vec();
+
+    bytes.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(&body);
+
+    bytes
+}
+
+///
+/// A 16 bit stereo PCM WAV file of interleaved `samples`
+///
+pub fn stereo_wav(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    wav(&[
+        fmt(1, 2, sample_rate, 16),
+        chunk(b"data", &samples_16(samples)),
+    ])
+}
+
+pub fn samples_16(samples: &[i16]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect()
+}
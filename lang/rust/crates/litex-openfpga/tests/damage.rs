@@ -0,0 +1,87 @@
+use litex_openfpga::*;
+
+#[test]
+fn copies_rows_missed_by_each_buffer() {
+    let mut damage = BufferDamage::<3>::new();
+
+    assert_eq!(damage.latest(), None);
+    assert_eq!(damage.stale_rows(0), 0..FRAMEBUFFER_HEIGHT);
+
+    damage.record(1, Rect::new(0, 0, 266, 240));
+    damage.record(2, Rect::new(10, 20, 5, 5));
+    damage.record(0, Rect::new(10, 100, 5, 10));
+
+    assert_eq!(damage.latest(), Some(0));
+    assert_eq!(damage.stale_rows(0), 0..0);
+    // Buffer 1 missed both partial frames, and buffer 2 only the last
+    assert_eq!(damage.stale_rows(1), 20..110);
+    assert_eq!(damage.stale_rows(2), 100..110);
+
+    // Damage off screen is ignored
+    damage.record(1, Rect::new(0, 230, 10, 50));
+
+    assert_eq!(damage.stale_rows(2), 100..240);
+    assert_eq!(damage.stale_rows(0), 230..240);
+}
+
+#[test]
+fn redraws_buffers_older_than_history() {
+    let mut damage = BufferDamage::<2>::new();
+
+    damage.record(0, Rect::new(0, 0, 1, 1));
+    damage.record(1, Rect::new(0, 5, 1, 1));
+    damage.record(1, Rect::new(0, 7, 1, 1));
+
+    assert_eq!(damage.stale_rows(0), 5..8);
+
+    damage.record(1, Rect::new(0, 9, 1, 1));
+
+    assert_eq!(damage.stale_rows(0), 0..FRAMEBUFFER_HEIGHT);
+}
+
+#[test]
+fn keeps_separate_regions() {
+    let mut damage = BufferDamage::<3>::new();
+    let bounds = Rect::new(0, 0, 100, 100);
+
+    let mut first = DirtyRegions::new(bounds);
+    first.add(Rect::new(0, 0, 10, 10));
+
+    let mut second = DirtyRegions::new(bounds);
+    second.add(Rect::new(50, 50, 10, 10));
+
+    damage.record_render(DirtyRegions::new(bounds));
+    damage.record_render(first);
+    damage.record_render(second);
+
+    assert_eq!(
+        damage.damage_since(2).rects(),
+        [Rect::new(0, 0, 10, 10), Rect::new(50, 50, 10, 10)]
+    );
+    assert_eq!(damage.damage_since(1).rects(), [Rect::new(50, 50, 10, 10)]);
+    // Unknown, and older than the first render
+    assert!(damage.damage_since(0).is_all());
+    assert!(damage.damage_since(3).is_all());
+    assert_eq!(damage.damage_since(3).bounds(), bounds);
+}
+
+#[test]
+fn counts_redrawn_area() {
+    let mut damage = BufferDamage::<2>::new();
+
+    damage.record(0, Rect::new(0, 0, 266, 240));
+    damage.record(1, Rect::new(0, 0, 266, 0));
+    damage.record_copy(0..12);
+
+    let stats = damage.stats();
+
+    assert_eq!(stats.frames, 2);
+    assert_eq!(stats.pixels_redrawn, FRAMEBUFFER_PIXELS as u64);
+    assert_eq!(stats.rows_copied, 12);
+    assert_eq!(stats.redrawn_fraction(), 0.5);
+    assert_eq!(stats.to_string(), "2 frames, 50.0% redrawn, 12 rows copied");
+
+    damage.reset_stats();
+
+    assert_eq!(damage.stats(), RedrawStats::default());
+}
@@ -0,0 +1,59 @@
+use litex_openfpga::*;
+
+#[test]
+fn counts_frames_and_drops() {
+    let mut clock = FrameClock::new();
+
+    assert_eq!(clock.tick(100), 1);
+    assert_eq!(clock.tick(101), 1);
+    assert!(!clock.missed_frame());
+    assert_eq!(clock.tick(101), 0);
+    assert_eq!(clock.tick(104), 3);
+    assert!(clock.missed_frame());
+    assert_eq!(clock.dropped_frames(), 2);
+    assert_eq!(clock.frames(), 5);
+    assert_eq!(clock.frame_counter(), 104);
+
+    clock.reset_dropped_frames();
+
+    assert_eq!(clock.dropped_frames(), 0);
+    assert_eq!(clock.delta(), 3);
+}
+
+#[test]
+fn handles_wraparound() {
+    let mut clock = FrameClock::new();
+
+    clock.tick(0x3FFF_FFFE);
+
+    assert_eq!(clock.tick(0x3FFF_FFFF), 1);
+    assert_eq!(clock.tick(0), 1);
+    assert_eq!(clock.tick(2), 2);
+    assert_eq!(clock.dropped_frames(), 1);
+    // Bits above the 30 bit counter are ignored
+    assert_eq!(clock.tick(0x4000_0003), 1);
+}
+
+#[test]
+fn steps_at_fixed_rate() {
+    // 25 steps per second at 60 Hz is 5 steps every 12 frames
+    let mut timestep = FixedTimestep::new(25, 60);
+    let steps: Vec<u32> = (0..12).map(|_| timestep.advance(1)).collect();
+
+    assert_eq!(steps.iter().sum::<u32>(), 5);
+    assert!(steps.iter().all(|&steps| steps <= 1));
+    assert_eq!(timestep.alpha(), 0.0);
+
+    let mut timestep = FixedTimestep::new(120, 60);
+
+    assert_eq!(timestep.advance(1), 2);
+    assert_eq!(timestep.advance(3), 6);
+
+    // Long stalls are capped, keeping the fraction
+    let mut timestep = FixedTimestep::new(90, 60).with_max_steps(4);
+
+    assert_eq!(timestep.advance(1), 1);
+    assert_eq!(timestep.alpha(), 0.5);
+    assert_eq!(timestep.advance(100), 4);
+    assert_eq!(timestep.alpha(), 0.5);
+}
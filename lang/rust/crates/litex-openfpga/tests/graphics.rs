@@ -0,0 +1,248 @@
+use std::time::{Duration, Instant};
+
+use litex_openfpga::*;
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 12;
+
+const RED: Rgb565 = Rgb565(0xF800);
+const BLUE: Rgb565 = Rgb565(0x001F);
+
+fn buffer() -> Vec<Rgb565> {
+    vec![Rgb565::BLACK; WIDTH * HEIGHT]
+}
+
+// Renders the canvas as one character per pixel, `.` for black and `#` for anything else
+fn render(canvas: &Canvas) -> Vec<String> {
+    canvas
+        .pixels()
+        .chunks(canvas.width())
+        .map(|row| {
+            row.iter()
+                .map(|&pixel| if pixel == Rgb565::BLACK { '.' } else { '#' })
+                .collect()
+        })
+        .collect()
+}
+
+fn count(canvas: &Canvas, color: Rgb565) -> usize {
+    canvas
+        .pixels()
+        .iter()
+        .filter(|&&pixel| pixel == color)
+        .count()
+}
+
+#[test]
+fn clips_rects() {
+    let mut pixels = buffer();
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+
+    canvas.fill_rect(Rect::new(-2, -2, 4, 3), RED);
+    canvas.fill_rect(Rect::new(14, 10, 10, 10), BLUE);
+    canvas.fill_rect(Rect::new(100, 0, 4, 4), BLUE);
+
+    assert_eq!(count(&canvas, RED), 2);
+    assert_eq!(count(&canvas, BLUE), 4);
+    assert_eq!(canvas.pixel(1, 0), Some(RED));
+    assert_eq!(canvas.pixel(15, 11), Some(BLUE));
+    assert_eq!(canvas.pixel(16, 0), None);
+
+    canvas.clear(Rgb565::BLACK);
+    canvas.draw_rect(Rect::new(1, 1, 4, 3), RED);
+
+    assert_eq!(
+        &render(&canvas)[..5],
+        [
+            "................",
+            ".####...........",
+            ".#..#...........",
+            ".####...........",
+            "................"
+        ]
+    );
+
+    assert_eq!(
+        Rect::new(0, 0, 4, 4).intersection(&Rect::new(2, 3, 4, 4)),
+        Rect::new(2, 3, 2, 1)
+    );
+    assert!(!Rect::new(0, 0, 4, 4).intersects(&Rect::new(4, 0, 4, 4)));
+}
+
+#[test]
+fn draws_lines() {
+    let mut pixels = buffer();
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+
+    canvas.draw_line(0, 0, 4, 2, RED);
+
+    assert_eq!(
+        &render(&canvas)[..3],
+        ["#...............", ".##.............", "...##..........."]
+    );
+
+    // Steep and reversed lines cover every row once, and clip
+    canvas.clear(Rgb565::BLACK);
+    canvas.draw_line(3, 11, 0, -5, RED);
+
+    assert_eq!(count(&canvas, RED), 12);
+
+    canvas.clear(Rgb565::BLACK);
+    canvas.draw_line(20, 3, -20, 3, RED);
+
+    assert_eq!(count(&canvas, RED), WIDTH);
+}
+
+#[test]
+fn draws_circles_once_per_pixel() {
+    let mut pixels = buffer();
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+
+    canvas.set_mode(DrawMode::Xor);
+
+    for radius in 0..6 {
+        canvas.draw_circle(7, 6, radius, RED);
+
+        let outline = count(&canvas, RED);
+
+        // Any pixel drawn twice would have been erased, leaving a gap in the outline
+        assert!(outline >= 4 * radius as usize);
+
+        canvas.draw_circle(7, 6, radius, RED);
+        canvas.fill_circle(7, 6, radius, BLUE);
+
+        assert!(count(&canvas, BLUE) > outline.saturating_sub(4) || radius == 0);
+
+        canvas.fill_circle(7, 6, radius, BLUE);
+
+        assert_eq!(count(&canvas, Rgb565::BLACK), WIDTH * HEIGHT);
+    }
+
+    canvas.set_mode(DrawMode::Replace);
+    canvas.draw_circle(7, 6, 2, RED);
+
+    assert_eq!(
+        &render(&canvas)[4..9],
+        [
+            "......###.......",
+            ".....#...#......",
+            ".....#...#......",
+            ".....#...#......",
+            "......###......."
+        ]
+    );
+}
+
+#[test]
+fn clips_extreme_shapes() {
+    let mut pixels = buffer();
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+
+    // Only the part inside the clip is walked, so these finish at once
+    canvas.draw_line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, RED);
+
+    assert_eq!(count(&canvas, RED), HEIGHT);
+    assert_eq!(canvas.pixel(5, 5), Some(RED));
+
+    canvas.clear(Rgb565::BLACK);
+    canvas.draw_line(i32::MIN, 4, i32::MAX, 4, RED);
+    canvas.draw_line(i32::MIN, i32::MIN, i32::MIN, i32::MAX, RED);
+
+    assert_eq!(count(&canvas, RED), WIDTH);
+
+    canvas.clear(Rgb565::BLACK);
+    canvas.set_clip(Rect::new(4, 2, 8, 8));
+    canvas.fill_circle(i32::MAX, 6, u32::MAX, BLUE);
+
+    assert_eq!(count(&canvas, BLUE), 64);
+
+    canvas.clear(Rgb565::BLACK);
+    canvas.fill_circle(i32::MIN, i32::MIN, 100, BLUE);
+    canvas.draw_circle(7, 6, u32::MAX, RED);
+    canvas.draw_circle(i32::MIN, i32::MAX, 1000, RED);
+
+    assert_eq!(count(&canvas, Rgb565::BLACK), WIDTH * HEIGHT);
+
+    // Only the rows within the clip are rasterized, however much of the outline lies outside
+    canvas.reset_clip();
+
+    let start = Instant::now();
+    canvas.draw_circle(-1_000_000_000, 6, 1_000_000_010, RED);
+
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert_eq!(count(&canvas, RED), HEIGHT);
+    assert!((0..HEIGHT as i32).all(|y| canvas.pixel(10, y) == Some(RED)));
+}
+
+#[test]
+fn blits() {
+    let mut pixels = buffer();
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+    let key = Rgb565(0xF81F);
+    #[rustfmt::skip]
+    let sprite = [
+        key, RED, key,
+        RED, BLUE, RED,
+    ];
+
+    canvas.blit(&sprite, 3, 0, 0, BlitMode::Copy);
+
+    assert_eq!(canvas.pixel(0, 0), Some(key));
+    assert_eq!(canvas.pixel(1, 1), Some(BLUE));
+
+    canvas.clear(Rgb565::BLACK);
+    canvas.blit(&sprite, 3, -1, 10, BlitMode::ColorKey(key));
+
+    assert_eq!(canvas.pixel(0, 10), Some(RED));
+    assert_eq!(canvas.pixel(1, 10), Some(Rgb565::BLACK));
+    assert_eq!(canvas.pixel(0, 11), Some(BLUE));
+    assert_eq!(canvas.pixel(1, 11), Some(RED));
+    assert_eq!(count(&canvas, Rgb565::BLACK), WIDTH * HEIGHT - 3);
+
+    // A region of the sheet, partly off the top left of the canvas
+    canvas.clear(Rgb565::BLACK);
+    canvas.blit_region(&sprite, 3, Rect::new(1, 0, 2, 2), -1, -1, BlitMode::Copy);
+
+    assert_eq!(canvas.pixel(0, 0), Some(RED));
+    assert_eq!(count(&canvas, Rgb565::BLACK), WIDTH * HEIGHT - 1);
+
+    canvas.blit(&sprite, 3, 4, 4, BlitMode::Xor);
+    canvas.blit(&sprite, 3, 4, 4, BlitMode::Xor);
+
+    assert_eq!(count(&canvas, Rgb565::BLACK), WIDTH * HEIGHT - 1);
+}
+
+#[cfg(feature = "embedded-graphics")]
+#[test]
+fn implements_draw_target() {
+    use litex_openfpga::embedded_graphics_core::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        pixelcolor::Rgb565 as EgRgb565,
+        primitives::Rectangle,
+        Pixel,
+    };
+
+    let mut pixels = buffer();
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+    let red = EgRgb565::from(RED);
+
+    canvas
+        .draw_iter([Pixel(Point::new(1, 1), red), Pixel(Point::new(-1, 1), red)])
+        .unwrap();
+    canvas
+        .fill_solid(&Rectangle::new(Point::new(14, 0), Size::new(4, 2)), red)
+        .unwrap();
+    // Colors for clipped pixels are skipped over
+    canvas
+        .fill_contiguous(
+            &Rectangle::new(Point::new(-1, 4), Size::new(2, 2)),
+            [red, EgRgb565::from(BLUE), red, EgRgb565::from(BLUE)],
+        )
+        .unwrap();
+
+    assert_eq!(canvas.pixel(1, 1), Some(RED));
+    assert_eq!(count(&canvas, RED), 5);
+    assert_eq!(canvas.pixel(0, 4), Some(BLUE));
+    assert_eq!(canvas.pixel(0, 5), Some(BLUE));
+}
@@ -0,0 +1,323 @@
+use litex_openfpga::*;
+
+#[test]
+fn decodes_key_register() {
+    // A docked analog gamepad holding up, A, and start
+    let key = (3 << 29) | (1 << 15) | (1 << 4) | 1;
+    let controller = Controller::from_bits(key, 0x80FF_0080, 0xFF00);
+
+    assert_eq!(
+        controller.controller_type,
+        ControllerType::DockedAnalogGamepad
+    );
+    assert_eq!(
+        controller.buttons,
+        Buttons::DPAD_UP | Buttons::FACE_A | Buttons::FACE_START
+    );
+    assert_eq!(
+        controller.joystick,
+        Joystick {
+            lstick_x: 0x80,
+            lstick_y: 0x00,
+            rstick_x: 0xFF,
+            rstick_y: 0x80,
+        }
+    );
+    assert_eq!(controller.joystick.to_bits(), 0x80FF_0080);
+    assert_eq!(
+        controller.triggers,
+        Triggers {
+            left: 0,
+            right: 0xFF
+        }
+    );
+
+    assert_eq!(ControllerType::from_key(0xFFFF), ControllerType::None);
+    assert_eq!(ControllerType::from_key(1 << 29), ControllerType::Pocket);
+    assert_eq!(
+        ControllerType::from_key(7 << 29),
+        ControllerType::Unknown(7)
+    );
+}
+
+#[test]
+fn button_sets() {
+    let held = Buttons::DPAD_LEFT | Buttons::FACE_B;
+
+    assert!(held.contains(Buttons::DPAD_LEFT));
+    assert!(!held.contains(Buttons::DPAD_LEFT | Buttons::DPAD_RIGHT));
+    assert!(held.intersects(Buttons::DPAD));
+    assert_eq!(held - Buttons::DPAD, Buttons::FACE_B);
+    assert_eq!((!held & Buttons::DPAD).bits(), 0b1011);
+    assert!(Buttons::from_key(0xE000_0000).is_empty());
+}
+
+// Steps one frame per entry, with controller 0 holding the given buttons
+fn script(state: &mut InputState, frames: &[u16]) {
+    for &bits in frames {
+        state.update_buttons(
+            [
+                Buttons::from_bits(bits),
+                Buttons::empty(),
+                Buttons::empty(),
+                Buttons::empty(),
+            ],
+            1,
+        );
+    }
+}
+
+#[test]
+fn edges_and_hold_times() {
+    let mut state = InputState::new();
+    let a = Buttons::FACE_A;
+    let b = Buttons::FACE_B;
+
+    script(&mut state, &[0x10]);
+    assert!(state.just_pressed(0, a));
+    assert!(!state.just_pressed(1, a));
+    assert_eq!(state.held_frames(0, a), 1);
+
+    script(&mut state, &[0x30]);
+    assert!(!state.just_pressed(0, a));
+    assert!(state.just_pressed(0, a | b));
+    assert_eq!(state.held_frames(0, a), 2);
+    assert_eq!(state.held_frames(0, b), 1);
+    assert_eq!(state.held_frames(0, a | b), 2);
+
+    script(&mut state, &[0x20]);
+    assert!(state.just_released(0, a));
+    assert!(!state.just_released(0, b));
+    assert!(state.is_held(0, b));
+    assert_eq!(state.held_frames(0, a), 0);
+
+    // Several frames passing between updates still count
+    state.update_buttons([b, Buttons::empty(), Buttons::empty(), Buttons::empty()], 5);
+    assert_eq!(state.held_frames(0, b), 7);
+
+    state.clear();
+    assert!(state.held(0).is_empty());
+}
+
+#[test]
+fn auto_repeat() {
+    let mut state = InputState::new().with_repeat(3, 2);
+    let mut fired = Vec::new();
+
+    for frame in 1..=10 {
+        script(&mut state, &[0x1]);
+
+        if state.repeated(0, Buttons::DPAD_UP) {
+            fired.push(frame);
+        }
+    }
+
+    // Pressed on frame 1, then repeats once held for more than 3 frames, every 2 frames
+    assert_eq!(fired, [1, 4, 6, 8, 10]);
+
+    // Skipped frames still fire a repeat when a threshold is crossed
+    state.update_buttons([Buttons::DPAD_UP; 4], 3);
+    assert!(state.repeated(0, Buttons::DPAD_UP));
+
+    state.set_repeat(3, 0);
+    script(&mut state, &[0, 0x1, 0x1, 0x1, 0x1, 0x1]);
+    assert!(!state.repeated(0, Buttons::DPAD_UP));
+}
+
+fn joystick(lstick_x: u8, lstick_y: u8) -> Joystick {
+    Joystick {
+        lstick_x,
+        lstick_y,
+        ..Joystick::CENTERED
+    }
+}
+
+#[test]
+fn stick_deadzones() {
+    let radial = Analog::new().with_deadzone(Deadzone::Radial(8192));
+    let axial = Analog::new().with_deadzone(Deadzone::Axial(8192));
+    let none = Analog::new().with_deadzone(Deadzone::None);
+
+    assert_eq!(none.left_stick(joystick(0x80, 0x80)), Stick::CENTERED);
+    assert_eq!(
+        none.left_stick(joystick(0xFF, 0x00)),
+        Stick {
+            x: 32767,
+            y: -32767
+        }
+    );
+
+    // Small movements are ignored, and full deflection is still reachable
+    assert!(radial.left_stick(joystick(0x90, 0x90)).is_centered());
+    assert_eq!(radial.left_stick(joystick(0xFF, 0x80)).x, 32767);
+    assert_eq!(axial.left_stick(joystick(0x00, 0x80)).x, -32767);
+
+    // A radial deadzone keeps a diagonal diagonal, an axial one snaps it to the larger axis
+    let diagonal = joystick(0xE0, 0x98);
+    let radial_diagonal = radial.left_stick(diagonal);
+
+    assert!(radial_diagonal.x > 0 && radial_diagonal.y > 0);
+    assert_eq!(axial.left_stick(diagonal).y, 0);
+    assert!((axial.left_stick(diagonal).x_f32() - 0.67).abs() < 0.01);
+}
+
+#[test]
+fn calibration_and_triggers() {
+    let mut analog = Analog::new().with_deadzone(Deadzone::None);
+
+    analog.calibrate(Joystick {
+        lstick_x: 0x70,
+        lstick_y: 0x90,
+        rstick_x: 0x80,
+        rstick_y: 0x80,
+    });
+
+    assert_eq!(analog.left_stick(joystick(0x70, 0x90)), Stick::CENTERED);
+    assert_eq!(
+        analog.left_stick(joystick(0xFF, 0xFF)),
+        Stick { x: 32767, y: 32767 }
+    );
+    assert_eq!(
+        analog.left_stick(joystick(0x00, 0x00)),
+        Stick {
+            x: -32767,
+            y: -32767
+        }
+    );
+
+    let analog = analog.with_trigger_deadzone(15);
+    let levels = analog.triggers(Triggers {
+        left: 10,
+        right: 0xFF,
+    });
+
+    assert_eq!(
+        levels,
+        TriggerLevels {
+            left: 0,
+            right: u16::MAX
+        }
+    );
+    assert_eq!(levels.right_f32(), 1.0);
+}
+
+#[test]
+fn stick_emulates_dpad() {
+    let analog = Analog::new().with_dpad_emulation(DEFAULT_DPAD_THRESHOLD);
+    let key = (3 << 29) | Buttons::FACE_A.bits() as u32;
+    let pushed = Controller::from_bits(key, joystick(0x10, 0xF0).to_bits(), 0);
+
+    assert_eq!(
+        analog.buttons(&pushed),
+        Buttons::FACE_A | Buttons::DPAD_LEFT | Buttons::DPAD_DOWN
+    );
+
+    // The handheld's sticks aren't real, so they never press the dpad
+    let pocket = Controller {
+        controller_type: ControllerType::Pocket,
+        ..pushed
+    };
+
+    assert_eq!(analog.buttons(&pocket), Buttons::FACE_A);
+    assert_eq!(Analog::new().buttons(&pushed), Buttons::FACE_A);
+}
+
+fn snapshot(key: u32, joy: u32) -> InputSnapshot {
+    InputSnapshot {
+        key: [key, 0, 0, 0],
+        joy: [joy, 0x8080_8080, 0, 0],
+        trig: [0; 4],
+    }
+}
+
+#[test]
+fn replay_reproduces_session() {
+    let session: Vec<(InputSnapshot, u32)> = (0..600u32)
+        .map(|frame| {
+            let key = (1 << 29) | if frame % 90 < 30 { 0x10 } else { 0 };
+            // Occasionally drop a frame
+            let elapsed = if frame % 100 == 99 { 2 } else { 1 };
+
+            (snapshot(key, 0x8080_8080 + frame / 200), elapsed)
+        })
+        .collect();
+
+    let mut recorder = InputRecorder::new(0x1234_5678);
+    let mut live = InputState::new();
+    let mut live_presses = Vec::new();
+
+    for &(snapshot, elapsed) in &session {
+        recorder.record_snapshot(snapshot, elapsed);
+        live.update_buttons(
+            core::array::from_fn(|index| snapshot.controller(index).buttons),
+            elapsed,
+        );
+        live_presses.push((
+            live.repeated(0, Buttons::FACE_A),
+            live.held_frames(0, Buttons::FACE_A),
+        ));
+    }
+
+    let bytes = recorder.to_bytes();
+
+    // Long stretches of unchanged input cost a few bytes each
+    assert!(bytes.len() < 200, "{} bytes", bytes.len());
+
+    let mut playback = InputPlayback::new(&bytes).unwrap();
+    let mut replayed = InputState::new();
+    let mut replayed_presses = Vec::new();
+
+    assert_eq!(playback.seed(), 0x1234_5678);
+    assert_eq!(playback.len(), 600);
+
+    for &(snapshot, _) in &session {
+        assert!(playback.advance());
+        assert_eq!(playback.snapshot(), snapshot);
+
+        replayed.update(&playback, playback.frame_counter());
+        replayed_presses.push((
+            replayed.repeated(0, Buttons::FACE_A),
+            replayed.held_frames(0, Buttons::FACE_A),
+        ));
+    }
+
+    assert!(!playback.advance());
+    assert!(playback.is_finished());
+    assert_eq!(replayed_presses, live_presses);
+
+    playback.rewind();
+    assert!(playback.advance());
+    assert_eq!(playback.snapshot(), session[0].0);
+}
+
+#[test]
+fn rejects_damaged_recordings() {
+    let mut recorder = InputRecorder::new(0);
+
+    recorder.record(&snapshot(0x1, 0), 10);
+    // A second call in the same frame is ignored
+    recorder.record(&snapshot(0x2, 0), 10);
+    recorder.record(&snapshot(0x3, 0), 11);
+
+    assert_eq!(recorder.len(), 2);
+
+    let bytes = recorder.to_bytes();
+
+    assert!(InputPlayback::new(&bytes).is_ok());
+    assert_eq!(
+        InputPlayback::new(&bytes[..bytes.len() - 1]).err(),
+        Some(ReplayError::Truncated)
+    );
+    assert_eq!(
+        InputPlayback::new(b"RIFF0000WAVEfmt ").err(),
+        Some(ReplayError::NotReplay)
+    );
+
+    let mut newer = bytes.clone();
+    newer[4] = 2;
+
+    assert_eq!(
+        InputPlayback::new(&newer).err(),
+        Some(ReplayError::UnsupportedVersion)
+    );
+}
@@ -0,0 +1,219 @@
+use litex_openfpga::*;
+
+interact_enum! {
+    /// The `list` options of a difficulty setting
+    pub enum Difficulty {
+        Easy = 0,
+        Normal = 1,
+        /// Not for the faint of heart
+        Hard = 0x10,
+    }
+}
+
+interact_settings! {
+    pub struct Settings {
+        /// A `check` entry
+        pub sound[0]: bool,
+        pub difficulty[2]: Difficulty,
+        volume[4]: u32,
+    }
+}
+
+#[test]
+fn enum_values() {
+    assert_eq!(Difficulty::from_interact(0x10), Difficulty::Hard);
+    assert_eq!(Difficulty::Normal.to_interact(), 1);
+    // Unknown values fall back to the first variant
+    assert_eq!(Difficulty::from_interact(7), Difficulty::Easy);
+    assert_eq!(Difficulty::default(), Difficulty::Easy);
+    assert_eq!(Difficulty::VARIANTS.len(), 3);
+}
+
+#[test]
+fn settings_apply_raw_values() {
+    let mut settings = Settings {
+        sound: false,
+        difficulty: Difficulty::Easy,
+        volume: 0,
+    };
+
+    assert_eq!(Settings::INDICES, &[0, 2, 4]);
+
+    assert!(settings.apply(0, 123));
+    assert!(settings.apply(2, 1));
+    assert!(settings.apply(4, 250));
+    assert!(!settings.apply(3, 1));
+
+    assert_eq!(
+        settings,
+        Settings {
+            sound: true,
+            difficulty: Difficulty::Normal,
+            volume: 250,
+        }
+    );
+}
+
+const OPTIONS: &[ListOption] = &[
+    ListOption {
+        value: 0,
+        name: "Off",
+    },
+    ListOption {
+        value: 2,
+        name: "\"Loud\"",
+    },
+];
+
+const DEFINITION: InteractDefinition = InteractDefinition::new(&[
+    InteractVariable::new(
+        0,
+        "Reset core",
+        InteractAddress::Core(0x1000_0000),
+        InteractKind::Action { value: 1 },
+    ),
+    InteractVariable::new(
+        10,
+        "Volume",
+        InteractAddress::Index(2),
+        InteractKind::List {
+            default: 2,
+            options: OPTIONS,
+        },
+    )
+    .persist(),
+    InteractVariable::new(
+        11,
+        "A",
+        InteractAddress::Index(15),
+        InteractKind::Radio {
+            group: 1,
+            value: 1,
+            default: true,
+        },
+    ),
+    InteractVariable::new(
+        12,
+        "B",
+        InteractAddress::Index(15),
+        InteractKind::Radio {
+            group: 1,
+            value: 2,
+            default: false,
+        },
+    ),
+]);
+
+#[test]
+fn renders_interact_json() {
+    let json = DEFINITION.to_json().unwrap();
+
+    assert!(json.starts_with("{\n  \"interact\": {\n    \"magic\": \"APF_VER_1\",\n"));
+    assert!(json.contains(
+        r#"      {
+        "name": "Volume",
+        "id": 10,
+        "type": "list",
+        "enabled": true,
+        "address": "0x10000108",
+        "persist": true,
+        "defaultval": 2,
+        "options": [
+          {
+            "value": 0,
+            "name": "Off"
+          },
+          {
+            "value": 2,
+            "name": "\"Loud\""
+          }
+        ]
+      },"#
+    ));
+    assert!(json.contains(r#""address": "0x1000013C""#));
+    assert!(json.ends_with("    ],\n    \"messages\": []\n  }\n}\n"));
+
+    assert_eq!(DEFINITION.variable_named("B").unwrap().id, 12);
+    assert_eq!(
+        DEFINITION.variable(10).unwrap().address.address(),
+        0x1000_0108
+    );
+}
+
+interact_settings! {
+    struct BoundSettings {
+        volume[DEFINITION.variables[1].index()]: u32,
+        mode[DEFINITION.variables[2].index()]: u32,
+    }
+}
+
+#[test]
+fn settings_bind_to_variables() {
+    let mut settings = BoundSettings { volume: 0, mode: 0 };
+
+    assert_eq!(BoundSettings::INDICES, &[2, 15]);
+    assert!(settings.apply(15, 2));
+    assert_eq!(settings.mode, 2);
+}
+
+#[test]
+fn rejects_invalid_definitions() {
+    let check = |address| {
+        InteractVariable::new(
+            1,
+            "Check",
+            address,
+            InteractKind::Check {
+                value: 1,
+                default: false,
+            },
+        )
+    };
+    let number = InteractVariable::new(
+        2,
+        "Number",
+        InteractAddress::Index(0),
+        InteractKind::NumberU32 { default: 0 },
+    );
+
+    let errors = [
+        [check(InteractAddress::Index(16)), number],
+        [check(InteractAddress::Core(0x1000_0002)), number],
+        [check(InteractAddress::Core(0x1000_0100)), number],
+        [check(InteractAddress::Index(0)), number],
+        [
+            check(InteractAddress::Index(1)),
+            InteractVariable { id: 1, ..number },
+        ],
+    ]
+    .map(|variables| InteractDefinition::new(Vec::leak(variables.to_vec())).validate());
+
+    assert_eq!(
+        errors,
+        [
+            Err(InteractDefinitionError::AddressOutOfRange(1)),
+            Err(InteractDefinitionError::AddressOutOfRange(1)),
+            Err(InteractDefinitionError::AddressOutOfRange(1)),
+            Err(InteractDefinitionError::AddressConflict(2)),
+            Err(InteractDefinitionError::DuplicateId(1)),
+        ]
+    );
+
+    let slider = InteractVariable::new(
+        3,
+        "Slider",
+        InteractAddress::Index(3),
+        InteractKind::SliderU32 {
+            default: 400,
+            min: 0,
+            max: 320,
+            adjust_small: 1,
+            adjust_large: 16,
+        },
+    );
+
+    assert_eq!(
+        InteractDefinition::new(Vec::leak(vec![slider])).to_json(),
+        Err(InteractDefinitionError::InvalidDefault(3))
+    );
+}
@@ -0,0 +1,118 @@
+use litex_openfpga::*;
+
+fn render<const N: usize>(mixer: &mut Mixer<'_, N>, length: usize) -> Vec<StereoSample> {
+    let mut output = vec![StereoSample::SILENCE; length];
+
+    mixer.render(&mut output);
+
+    output
+}
+
+#[test]
+fn idle_mixer_is_silent() {
+    let mut mixer = Mixer::<4>::new();
+
+    assert!(render(&mut mixer, 64)
+        .iter()
+        .all(|sample| *sample == StereoSample::SILENCE));
+}
+
+#[test]
+fn square_wave_period() {
+    let mut mixer = Mixer::<4>::new();
+
+    // 480Hz is exactly 100 samples per cycle
+    let voice = mixer.play(Sound::tone(Waveform::Square, 480)).unwrap();
+    let output = render(&mut mixer, 200);
+
+    assert!(output[..50].iter().all(|sample| sample.left > 30000));
+    assert!(output[50..100].iter().all(|sample| sample.left < -30000));
+    assert_eq!(output[..100], output[100..]);
+
+    mixer.stop(voice);
+
+    assert!(!mixer.is_playing(voice));
+}
+
+#[test]
+fn pan_and_volume() {
+    let mut mixer = Mixer::<2>::new();
+
+    mixer.play(
+        Sound::tone(Waveform::Square, 480)
+            .with_pan(i8::MIN)
+            .with_volume(128),
+    );
+
+    let sample = render(&mut mixer, 1)[0];
+
+    assert_eq!(sample.right, 0);
+    assert!((16000..17000).contains(&sample.left));
+}
+
+#[test]
+fn envelope_releases_after_duration() {
+    let mut mixer = Mixer::<1>::new();
+    let envelope = Envelope::new(10, 10, u16::MAX / 2, 20);
+    let voice = mixer
+        .play(
+            Sound::tone(Waveform::Square, 480)
+                .with_envelope(envelope)
+                .with_duration(40),
+        )
+        .unwrap();
+
+    let output = render(&mut mixer, 100);
+
+    // Ramps up from silence, then holds at half volume
+    assert_eq!(output[0].left, 0);
+    assert!(output[5].left > 0 && output[5].left < output[10].left);
+    assert!((16000..16500).contains(&output[30].left));
+
+    assert!(output[61..].iter().all(|sample| sample.left == 0));
+    assert!(!mixer.is_playing(voice));
+
+    // The voice is free again
+    assert!(mixer.play(Sound::tone(Waveform::Saw, 100)).is_some());
+}
+
+#[test]
+fn pcm_playback() {
+    let samples = [1000, 2000, 3000, 4000];
+    let mut mixer = Mixer::<2>::new();
+
+    let once = mixer
+        .play(Sound::pcm(Pcm::new(&samples, SAMPLE_RATE)))
+        .unwrap();
+    let output = render(&mut mixer, 6);
+
+    assert_eq!(
+        output.iter().map(|sample| sample.left).collect::<Vec<_>>(),
+        [1000, 2000, 3000, 4000, 0, 0]
+    );
+    assert!(!mixer.is_playing(once));
+
+    // Half rate, looping from the second sample
+    mixer.play(Sound::pcm(Pcm::new(&samples, SAMPLE_RATE / 2).looping(1)));
+
+    let output = render(&mut mixer, 10);
+
+    assert_eq!(
+        output.iter().map(|sample| sample.left).collect::<Vec<_>>(),
+        [1000, 1000, 2000, 2000, 3000, 3000, 4000, 4000, 2000, 2000]
+    );
+}
+
+#[test]
+fn voices_are_limited() {
+    let mut mixer = Mixer::<2>::new();
+
+    assert!(mixer.play(Sound::tone(Waveform::Noise, 1000)).is_some());
+    assert!(mixer.play(Sound::tone(Waveform::Triangle, 1000)).is_some());
+    assert!(mixer.play(Sound::tone(Waveform::Saw, 1000)).is_none());
+    assert_eq!(mixer.active_voices(), 2);
+
+    mixer.stop_all();
+
+    assert_eq!(mixer.active_voices(), 0);
+}
@@ -0,0 +1,194 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use core::task::Poll;
+use std::fs;
+
+use common::*;
+use embedded_io::Read;
+use litex_openfpga::*;
+
+fn test_data(length: u32) -> Vec<u8> {
+    (0..length).map(|i| (i * 7) as u8).collect()
+}
+
+#[test]
+fn read_into_unaligned_buffer() {
+    let directory = slot_directory("read-into");
+    let data = test_data(1000);
+    fs::write(directory.join("3.bin"), &data).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory));
+    let mut buffer = [0u8; 302];
+
+    assert_eq!(file.size(3), 1000);
+    assert_eq!(file.read_into(3, 5, &mut buffer[1..301]), Ok(300));
+    assert_eq!(&buffer[1..301], &data[5..305]);
+
+    // Whole word transfers must not spill outside of the slice
+    assert_eq!(buffer[0], 0);
+    assert_eq!(buffer[301], 0);
+
+    // Reads are clamped to the end of the file
+    assert_eq!(file.read_into(3, 990, &mut buffer), Ok(10));
+    assert_eq!(&buffer[..10], &data[990..]);
+    assert_eq!(
+        file.read_into(3, 2000, &mut buffer),
+        Err(ReadError::OffsetPastEnd)
+    );
+}
+
+#[test]
+fn slow_transfer_reports_progress() {
+    let directory = slot_directory("progress");
+    fs::write(directory.join("0.bin"), test_data(256)).unwrap();
+
+    let mut file = File::new(
+        MockBridge::new(&directory)
+            .with_latency(2)
+            .with_chunk_size(64),
+    );
+    let mut buffer = [0u32; 64];
+    let mut transfer = file.request_read(0, 256, buffer.as_mut_ptr() as usize, 0);
+    let mut polls = 0;
+    let mut progress = 0;
+
+    while transfer.poll().is_pending() {
+        polls += 1;
+        assert!(transfer.progress() >= progress);
+        assert!(transfer.progress() <= transfer.length());
+        progress = transfer.progress();
+    }
+
+    // The last chunk completes the transfer, so the bytes before it were seen in progress
+    assert_eq!(progress, 192);
+
+    assert_eq!(polls, 5);
+    assert_eq!(transfer.poll(), Poll::Ready(Ok(256)));
+}
+
+#[test]
+fn slot_reader_matches_file() {
+    let directory = slot_directory("slot-reader");
+    let data = test_data(3000);
+    fs::write(directory.join("1.bin"), &data).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory).with_chunk_size(100));
+    let mut reader = file.slot_reader(1);
+    let mut output = vec![0u8; 3000];
+
+    reader.read_exact(&mut output[..7]).unwrap();
+    reader.read_exact(&mut output[7..]).unwrap();
+
+    assert_eq!(output, data);
+}
+
+#[test]
+fn injected_errors() {
+    let directory = slot_directory("errors");
+    fs::write(directory.join("1.bin"), test_data(64)).unwrap();
+
+    let bridge = MockBridge::new(&directory);
+    bridge.fail_next(3, 8);
+
+    let mut file = File::new(bridge);
+    let mut buffer = [0u8; 32];
+
+    assert_eq!(
+        file.read_into(1, 0, &mut buffer),
+        Err(ReadError::Bridge(BridgeError::FileError))
+    );
+    // A slot without a file reads as empty
+    assert_eq!(file.read_into(2, 0, &mut buffer), Ok(0));
+    assert_eq!(
+        file.write_from(2, 0, &buffer),
+        Err(BridgeError::SlotNotFound)
+    );
+    // Writes can't grow the file
+    assert_eq!(
+        file.write_from(1, 48, &buffer),
+        Err(BridgeError::OutOfRange)
+    );
+
+    file.set_size(1, 80);
+
+    assert_eq!(file.write_from(1, 48, &buffer), Ok(()));
+}
+
+#[test]
+fn paths() {
+    let directory = slot_directory("paths");
+    fs::write(directory.join("1.bin"), []).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory));
+
+    assert_eq!(file.get_path(1).unwrap().as_str(), "1.bin");
+    assert_eq!(
+        file.get_path(2),
+        Err(PathError::Bridge(BridgeError::SlotNotFound))
+    );
+    assert_eq!(
+        file.open_path(2, "missing.sav"),
+        Err(PathError::Bridge(BridgeError::FileError))
+    );
+
+    let flags = OpenFlags {
+        create: true,
+        resize: true,
+        size: 100,
+    };
+
+    file.open_path_with(2, "game.sav", flags).unwrap();
+
+    assert_eq!(file.get_path(2).unwrap().as_str(), "game.sav");
+    assert_eq!(file.size(2), 100);
+}
+
+#[test]
+fn save_slot_recovers_from_corruption() {
+    let directory = slot_directory("save");
+    fs::write(directory.join("5.bin"), []).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory));
+    let mut save = SaveSlot::new(5, 1, 100);
+    let mut buffer = [0u8; 100];
+
+    assert_eq!(save.load(&mut file, &mut buffer), Ok(None));
+
+    save.store(&mut file, b"first").unwrap();
+    save.store(&mut file, b"second").unwrap();
+
+    let path = file.free().slot_path(5);
+    let mut raw = fs::read(&path).unwrap();
+
+    assert_eq!(raw.len() as u32, save.file_size());
+
+    // Corrupt the data of the newer, second copy
+    let second_copy = raw.len() / 2;
+    raw[second_copy + 24] ^= 0xFF;
+    fs::write(&path, &raw).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory));
+    let mut save = SaveSlot::new(5, 1, 100);
+
+    assert_eq!(
+        save.load(&mut file, &mut buffer),
+        Ok(Some(SaveInfo {
+            version: 1,
+            length: 5
+        }))
+    );
+    assert_eq!(&buffer[..5], b"first");
+
+    // The damaged copy is the one replaced
+    save.store(&mut file, b"third").unwrap();
+
+    let mut save = SaveSlot::new(5, 1, 100);
+
+    assert_eq!(
+        save.load(&mut file, &mut buffer).unwrap().unwrap().length,
+        5
+    );
+    assert_eq!(&buffer[..5], b"third");
+}
@@ -0,0 +1,141 @@
+use core::time::Duration;
+
+use litex_openfpga::*;
+
+fn date_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+    DateTime {
+        date: Date::new(year, month, day).unwrap(),
+        time: Time::new(hour, minute, second).unwrap(),
+    }
+}
+
+#[test]
+fn converts_unix_seconds() {
+    let cases = [
+        (0, date_time(1970, 1, 1, 0, 0, 0), Weekday::Thursday),
+        (
+            951_782_400,
+            date_time(2000, 2, 29, 0, 0, 0),
+            Weekday::Tuesday,
+        ),
+        (
+            1_709_251_199,
+            date_time(2024, 2, 29, 23, 59, 59),
+            Weekday::Thursday,
+        ),
+        (
+            1_709_251_200,
+            date_time(2024, 3, 1, 0, 0, 0),
+            Weekday::Friday,
+        ),
+        (
+            1_735_689_599,
+            date_time(2024, 12, 31, 23, 59, 59),
+            Weekday::Tuesday,
+        ),
+        (
+            4_107_542_400,
+            date_time(2100, 3, 1, 0, 0, 0),
+            Weekday::Monday,
+        ),
+        (u32::MAX, date_time(2106, 2, 7, 6, 28, 15), Weekday::Sunday),
+    ];
+
+    for (seconds, expected, weekday) in cases {
+        let converted = DateTime::from_unix_seconds(seconds);
+
+        assert_eq!(converted, expected);
+        assert_eq!(converted.weekday(), weekday);
+        assert_eq!(converted.to_unix_seconds(), Some(seconds));
+    }
+
+    assert_eq!(
+        DateTime::from_unix_seconds(1_709_251_199).to_string(),
+        "2024-02-29 23:59:59"
+    );
+    assert_eq!(date_time(1969, 12, 31, 23, 59, 59).to_unix_seconds(), None);
+    assert_eq!(date_time(2106, 2, 7, 6, 28, 16).to_unix_seconds(), None);
+}
+
+#[test]
+fn handles_leap_years() {
+    assert!(is_leap_year(2000));
+    assert!(is_leap_year(2024));
+    assert!(!is_leap_year(1900));
+    assert!(!is_leap_year(2100));
+    assert!(!is_leap_year(2023));
+
+    assert_eq!(Date::new(2000, 2, 29).map(|date| date.ordinal()), Some(60));
+    assert_eq!(
+        Date::new(2024, 12, 31).map(|date| date.ordinal()),
+        Some(366)
+    );
+    assert_eq!(Date::new(2100, 2, 29), None);
+    assert_eq!(Date::new(2023, 2, 29), None);
+
+    // Every day of a leap century round trips
+    for days in Date::new(1999, 12, 1).unwrap().days_since_epoch()
+        ..Date::new(2001, 2, 1).unwrap().days_since_epoch()
+    {
+        let date = Date::from_days_since_epoch(days);
+
+        assert_eq!(Date::new(date.year, date.month, date.day), Some(date));
+        assert_eq!(date.days_since_epoch(), days);
+    }
+
+    assert_eq!(
+        DateTime::from_unix_seconds(4_107_456_000).date,
+        Date::new(2100, 2, 28).unwrap()
+    );
+}
+
+#[test]
+fn decodes_bcd() {
+    assert_eq!(decode_bcd(0x1234, 4), Some(1234));
+    assert_eq!(decode_bcd(0x0009, 2), Some(9));
+    assert_eq!(decode_bcd(0x001A, 2), None);
+    assert_eq!(decode_bcd(0xF0, 1), Some(0));
+
+    assert_eq!(Date::from_bcd(0x2024_0229), Date::new(2024, 2, 29));
+    assert_eq!(Date::from_bcd(0x2023_0229), None);
+    assert_eq!(Date::from_bcd(0x2024_1231), Date::new(2024, 12, 31));
+    assert_eq!(Date::from_bcd(0x2024_1301), None);
+    assert_eq!(Date::from_bcd(0x2024_0100), None);
+    assert_eq!(Date::from_bcd(0x2024_0A01), None);
+    assert_eq!(Date::from_bcd(0x0000_0000), None);
+
+    assert_eq!(Time::from_bcd(0x23_5959), Time::new(23, 59, 59));
+    assert_eq!(Time::from_bcd(0x00_0000), Time::new(0, 0, 0));
+    // The top byte is ignored
+    assert_eq!(Time::from_bcd(0xFF12_3456), Time::new(12, 34, 56));
+    assert_eq!(Time::from_bcd(0x24_0000), None);
+    assert_eq!(Time::from_bcd(0x12_6000), None);
+    assert_eq!(Time::from_bcd(0x12_001F), None);
+}
+
+#[test]
+fn wall_clock_tracks_sub_seconds() {
+    let mut clock = WallClock::new();
+    let ms = Duration::from_millis;
+
+    // Before a tick, the fraction counts from the first observation and stops short of the next second
+    assert_eq!(clock.observe(100, ms(5_000)), Duration::from_secs(100));
+    assert!(!clock.is_synchronized());
+    assert_eq!(clock.observe(100, ms(5_400)), ms(100_400));
+    assert_eq!(
+        clock.observe(100, ms(6_200)),
+        ms(100_999) + Duration::from_nanos(999_999)
+    );
+
+    // The tick pins the start of the second, without running backwards
+    assert_eq!(clock.observe(101, ms(6_300)), ms(101_000));
+    assert!(clock.is_synchronized());
+    assert_eq!(clock.observe(101, ms(6_550)), ms(101_250));
+    assert_eq!(clock.observe(102, ms(7_290)), ms(102_000));
+    assert_eq!(clock.observe(102, ms(7_300)), ms(102_010));
+
+    // Jumps are followed, even backwards
+    assert_eq!(clock.observe(50, ms(7_400)), Duration::from_secs(50));
+    assert!(!clock.is_synchronized());
+    assert_eq!(clock.observe(50, ms(7_500)), ms(50_100));
+}
@@ -0,0 +1,221 @@
+#[cfg(feature = "std")]
+mod common;
+
+use litex_openfpga::*;
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+
+const KEY: Rgb565 = Rgb565(0xF81F);
+const POISON: Rgb565 = Rgb565(0x1234);
+const BACKGROUND: Rgb565 = Rgb565(0x0001);
+
+// Tile `n` of a 4x4 tile sheet is filled with color `n + 1`, except tile 3, which has a transparent left half
+fn tileset() -> Tileset {
+    let mut pixels = vec![Rgb565::BLACK; 16 * 4];
+
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (index % 16, index / 16);
+        let tile = x / 4;
+
+        *pixel = if tile == 3 && x % 4 < 2 {
+            KEY
+        } else {
+            Rgb565(tile as u16 + 1)
+        };
+
+        // Mark the top left corner of opaque tiles, to check flips
+        if x % 4 == 0 && y == 0 && tile != 3 {
+            pixel.0 |= 0x100;
+        }
+    }
+
+    Tileset::new(pixels, 16, 4, 4).with_color_key(KEY)
+}
+
+fn scene() -> Scene {
+    let mut scene = Scene::new(WIDTH as u32, HEIGHT as u32).with_background(BACKGROUND);
+    let tiles = scene.add_tileset(tileset());
+
+    let mut layer = TileLayer::new(tiles, 8, 6);
+
+    layer.fill(0);
+    scene.add_layer(layer);
+
+    scene
+}
+
+#[test]
+fn merges_dirty_regions() {
+    let mut dirty = DirtyRegions::new(Rect::new(0, 0, 100, 100));
+
+    dirty.add(Rect::new(0, 0, 10, 10));
+    dirty.add(Rect::new(50, 50, 10, 10));
+    dirty.add(Rect::new(-20, -20, 5, 5));
+
+    assert_eq!(dirty.rects().len(), 2);
+
+    // Bridging both merges all three
+    dirty.add(Rect::new(5, 5, 50, 50));
+
+    assert_eq!(dirty.rects(), [Rect::new(0, 0, 60, 60)]);
+    assert!(!dirty.is_all());
+
+    dirty.clear();
+
+    for index in 0..MAX_DIRTY_RECTS as i32 + 1 {
+        dirty.add(Rect::new(index * 5, 0, 2, 2));
+    }
+
+    assert_eq!(dirty.rects().len(), 1);
+
+    dirty.add_all();
+
+    assert!(dirty.is_all());
+}
+
+#[test]
+fn draws_layers_and_sprites() {
+    let mut scene = scene();
+    let mut pixels = vec![POISON; WIDTH * HEIGHT];
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+
+    let sprite = scene.add_sprite(Sprite::new(0, 3, 2, 2));
+
+    assert!(scene.render(&mut canvas, 0).is_all());
+    assert!(canvas.pixels().iter().all(|&pixel| pixel != POISON));
+    assert_eq!(canvas.pixel(2, 3), Some(Rgb565(1)));
+    // The transparent half of the sprite shows the layer beneath
+    assert_eq!(canvas.pixel(4, 3), Some(Rgb565(4)));
+
+    scene.sprite_mut(sprite).flip = Flip::BOTH;
+    scene.render(&mut canvas, 1);
+
+    assert_eq!(canvas.pixel(2, 3), Some(Rgb565(4)));
+    assert_eq!(canvas.pixel(5, 5), Some(Rgb565(1)));
+    assert_eq!(canvas.pixel(4, 3), Some(Rgb565(1)));
+}
+
+#[test]
+fn redraws_only_changes() {
+    let mut scene = scene();
+    let mut pixels = vec![POISON; WIDTH * HEIGHT];
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+    let sprite = scene.add_sprite(Sprite::new(0, 2, 0, 0));
+
+    scene.render(&mut canvas, 0);
+    canvas.clear(POISON);
+
+    // Nothing changed
+    assert!(scene.render(&mut canvas, 1).is_empty());
+
+    scene.sprite_mut(sprite).x = 2;
+
+    let dirty = scene.render(&mut canvas, 1);
+
+    assert_eq!(dirty.rects(), [Rect::new(0, 0, 6, 4)]);
+    assert_eq!(canvas.pixel(0, 0), Some(Rgb565(0x101)));
+    assert_eq!(canvas.pixel(2, 1), Some(Rgb565(3)));
+    assert_eq!(canvas.pixel(6, 0), Some(POISON));
+
+    scene.layer_mut(0).set_tile(7, 5, 1);
+
+    let dirty = scene.render(&mut canvas, 1);
+
+    assert_eq!(dirty.rects(), [Rect::new(28, 20, 4, 4)]);
+    assert_eq!(canvas.pixel(31, 23), Some(Rgb565(2)));
+
+    // A buffer two renders old also needs the sprite move
+    scene.sprite_mut(sprite).visible = false;
+
+    let dirty = scene.render(&mut canvas, 2);
+
+    assert_eq!(dirty.rects().len(), 2);
+    assert_eq!(canvas.pixel(2, 1), Some(Rgb565(1)));
+
+    // Buffers older than every render so far are redrawn in full
+    assert!(scene.render(&mut canvas, 6).is_all());
+}
+
+#[test]
+fn scrolls_and_wraps() {
+    let mut scene = Scene::new(WIDTH as u32, HEIGHT as u32).with_background(BACKGROUND);
+    let tiles = scene.add_tileset(tileset());
+    let mut layer = TileLayer::new(tiles, 2, 1).with_wrap(true);
+
+    layer.set_tiles(&[0, 1]);
+    scene.add_layer(layer);
+
+    let mut pixels = vec![POISON; WIDTH * HEIGHT];
+    let mut canvas = Canvas::new(&mut pixels, WIDTH, HEIGHT);
+
+    scene.layer_mut(0).set_scroll(-1, 0);
+    scene.render(&mut canvas, 0);
+
+    assert_eq!(canvas.pixel(0, 0), Some(Rgb565(2)));
+    assert_eq!(canvas.pixel(1, 23), Some(Rgb565(1)));
+    assert_eq!(canvas.pixel(9, 9), Some(Rgb565(1)));
+
+    // A changed tile is redrawn everywhere it repeats
+    scene.layer_mut(0).set_tile(1, 0, 2);
+
+    let dirty = scene.render(&mut canvas, 1);
+
+    assert_eq!(canvas.pixel(0, 0), Some(Rgb565(3)));
+    assert_eq!(canvas.pixel(29, 12), Some(Rgb565(0x103)));
+    assert_eq!(canvas.pixel(1, 0), Some(Rgb565(0x101)));
+    assert!(dirty.rects().len() > 1);
+
+    // Without wrapping, the background shows around the layer
+    let mut scene = scene_without_wrap();
+
+    scene.render(&mut canvas, 0);
+
+    assert_eq!(canvas.pixel(0, 0), Some(BACKGROUND));
+    assert_eq!(canvas.pixel(4, 4), Some(Rgb565(0x101)));
+    assert_eq!(canvas.pixel(12, 4), Some(BACKGROUND));
+}
+
+fn scene_without_wrap() -> Scene {
+    let mut scene = Scene::new(WIDTH as u32, HEIGHT as u32).with_background(BACKGROUND);
+    let tiles = scene.add_tileset(tileset());
+    let mut layer = TileLayer::new(tiles, 2, 1);
+
+    layer.set_tiles(&[0, 1]);
+    layer.set_scroll(-4, -4);
+    scene.add_layer(layer);
+
+    scene
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn loads_tileset_from_slot() {
+    use std::fs;
+
+    let directory = common::slot_directory("tileset");
+
+    // Two 2x2 tiles side by side
+    let pixels: [u16; 8] = [1, 1, 2, 2, 1, 1, 2, 0xF81F];
+    let bytes: Vec<u8> = pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_le_bytes())
+        .collect();
+
+    fs::write(directory.join("4.bin"), bytes).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory));
+    let tileset = Tileset::load(&mut file, 4, 4, 2, 2).unwrap();
+
+    assert_eq!(tileset.len(), 2);
+    assert_eq!(tileset.tile_region(1), Rect::new(2, 0, 2, 2));
+
+    let tileset = tileset.with_color_key(KEY);
+    let mut canvas_pixels = vec![POISON; 4];
+    let mut canvas = Canvas::new(&mut canvas_pixels, 2, 2);
+
+    tileset.draw_tile(&mut canvas, 1, 0, 0, Flip::NONE);
+    tileset.draw_tile(&mut canvas, EMPTY_TILE, 0, 0, Flip::NONE);
+
+    assert_eq!(canvas.pixels(), [Rgb565(2), Rgb565(2), Rgb565(2), POISON]);
+}
@@ -0,0 +1,145 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::fs;
+
+use common::*;
+use litex_openfpga::*;
+
+fn ramp(frames: i16) -> Vec<i16> {
+    (0..frames).flat_map(|i| [i, -i]).collect()
+}
+
+///
+/// Streams until `length` samples have been decoded or the stream ends, as a game loop would
+///
+fn stream<const N: usize, B: BridgeBackend>(
+    player: &mut StreamPlayer<'_, N, B>,
+    length: usize,
+) -> Vec<StereoSample> {
+    let mut output = Vec::new();
+    let mut frame = [StereoSample::SILENCE; 100];
+
+    while output.len() < length && !player.is_finished() {
+        player.pump().unwrap();
+
+        let count = player.decode(&mut frame[..(length - output.len()).min(100)]);
+        output.extend_from_slice(&frame[..count]);
+    }
+
+    player.wait_for_read().unwrap();
+
+    output
+}
+
+#[test]
+fn matches_whole_file_decode() {
+    let directory = slot_directory("whole");
+    fs::write(directory.join("0.bin"), stereo_wav(32_000, &ramp(3000))).unwrap();
+
+    let mut file = File::new(
+        MockBridge::new(&directory)
+            .with_latency(3)
+            .with_chunk_size(100),
+    );
+    let expected = file.load_wav(0).unwrap();
+
+    let mut buffers = StreamBuffers::<256>::new();
+    let mut player = StreamPlayer::open(&mut file, 0, &mut buffers).unwrap();
+    player.play();
+
+    let output = stream(&mut player, usize::MAX);
+
+    assert_eq!(output.len(), expected.len());
+    assert_eq!(output, expected);
+    assert!(player.is_finished());
+    assert!(!player.is_playing());
+}
+
+#[test]
+fn loops_without_gaps() {
+    let directory = slot_directory("loop");
+    fs::write(directory.join("0.bin"), stereo_wav(48_000, &ramp(1000))).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory).with_latency(2));
+    let mut buffers = StreamBuffers::<256>::new();
+    let mut player = StreamPlayer::open(&mut file, 0, &mut buffers).unwrap();
+    player.set_looping(true);
+    player.play();
+
+    let output = stream(&mut player, 2500);
+    let expected: Vec<_> = (0..2500)
+        .map(|i| {
+            let value = (i % 1000) as i16;
+
+            StereoSample::new(value, -value)
+        })
+        .collect();
+
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn nothing_decodes_until_the_bridge_delivers() {
+    let directory = slot_directory("latency");
+    fs::write(directory.join("0.bin"), stereo_wav(48_000, &ramp(100))).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory).with_latency(5));
+    let mut buffers = StreamBuffers::<64>::new();
+    let mut player = StreamPlayer::open(&mut file, 0, &mut buffers).unwrap();
+    let mut output = [StereoSample::SILENCE; 10];
+
+    // The first chunk is read from the start
+    assert!(player.is_reading());
+
+    player.play();
+    player.pump().unwrap();
+
+    assert!(player.is_reading());
+    assert_eq!(player.decode(&mut output), 0);
+    // Waiting for the first chunk isn't starvation
+    assert_eq!(player.starved(), 0);
+
+    // The bridge is busy, so the read finishes before the file can be used for anything else
+    assert_eq!(player.file().unwrap().size(0), 44 + 400);
+    assert!(!player.is_reading());
+
+    assert_eq!(player.decode(&mut output), 10);
+    assert_eq!(output[9], StereoSample::new(9, -9));
+
+    // Running out of the first chunk before the next arrives is
+    player.pump().unwrap();
+
+    while player.decode(&mut output) > 0 {}
+
+    assert!(player.is_reading());
+    assert_eq!(player.starved(), 1);
+
+    player.stop().unwrap();
+    player.pump().unwrap();
+    player.wait_for_read().unwrap();
+
+    assert_eq!(player.decode(&mut output[..1]), 1);
+    assert_eq!(output[0], StereoSample::new(0, 0));
+}
+
+#[test]
+fn dropping_waits_for_the_read() {
+    let directory = slot_directory("drop");
+    fs::write(directory.join("0.bin"), stereo_wav(48_000, &ramp(100))).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory).with_latency(5));
+    let mut buffers = StreamBuffers::<64>::new();
+    let mut player = StreamPlayer::open(&mut file, 0, &mut buffers).unwrap();
+
+    player.pump().unwrap();
+    assert!(player.is_reading());
+    drop(player);
+
+    // The bridge is free again, so the next operation can't be mistaken for the abandoned read
+    let mut header = [0u8; 4];
+
+    assert_eq!(file.read_into(0, 0, &mut header), Ok(4));
+    assert_eq!(&header, b"RIFF");
+}
@@ -0,0 +1,155 @@
+use std::cell::Cell;
+
+use litex_openfpga::*;
+
+#[test]
+fn converts_rgb565() {
+    assert_eq!(Rgb565::from_rgb888(0xFF, 0xFF, 0xFF), Rgb565::WHITE);
+    assert_eq!(Rgb565::from_rgb888(0, 0, 0), Rgb565::BLACK);
+    assert_eq!(Rgb565::from_rgb888(0xFF, 0, 0), Rgb565(0xF800));
+    assert_eq!(Rgb565::from_rgb888(0, 0xFF, 0), Rgb565(0x07E0));
+    assert_eq!(Rgb565::from_rgb888(0, 0, 0xFF), Rgb565(0x001F));
+    // Low bits are dropped
+    assert_eq!(Rgb565::from_rgb888(0x07, 0x03, 0x07), Rgb565::BLACK);
+
+    assert_eq!(Rgb565::WHITE.to_rgb888(), (0xFF, 0xFF, 0xFF));
+    assert_eq!(Rgb565(0x8410).to_rgb888(), (0x84, 0x82, 0x84));
+    assert_eq!(u16::from(Rgb565(0x1234)), 0x1234);
+}
+
+#[test]
+fn lays_out_buffers() {
+    assert_eq!(FRAMEBUFFER_PIXELS, 266 * 240);
+    assert_eq!(
+        Framebuffers::<3>::buffer_address(0),
+        litex_pac::constants::VIDEO_FRAMEBUFFER_BASE
+    );
+    assert_eq!(
+        Framebuffers::<3>::buffer_address(2) - Framebuffers::<3>::buffer_address(1),
+        (FRAMEBUFFER_PIXELS * 2) as u32
+    );
+}
+
+#[test]
+fn counts_frames_across_wrap_around() {
+    assert_eq!(frames_since(None, 5), 1);
+    assert_eq!(frames_since(Some(5), 5), 0);
+    assert_eq!(frames_since(Some(5), 8), 3);
+    assert_eq!(frames_since(Some(FRAME_COUNTER_MASK), 1), 2);
+}
+
+// A display whose vblanks are started by the test, or on every status read when free running
+#[derive(Default)]
+struct MockVideo {
+    frame_counter: Cell<u32>,
+    vblank: Cell<bool>,
+    vblank_triggered: Cell<bool>,
+    free_running: bool,
+    address: u32,
+}
+
+impl MockVideo {
+    fn free_running() -> Self {
+        MockVideo {
+            free_running: true,
+            ..MockVideo::default()
+        }
+    }
+
+    fn start_vblank(&self) {
+        self.frame_counter.set(self.frame_counter.get() + 1);
+        self.vblank.set(true);
+        self.vblank_triggered.set(true);
+    }
+
+    fn end_vblank(&self) {
+        self.vblank.set(false);
+    }
+}
+
+impl VideoBackend for MockVideo {
+    fn status(&self) -> VideoStatus {
+        if self.free_running {
+            self.start_vblank();
+        }
+
+        VideoStatus {
+            vblank: self.vblank.get(),
+            vblank_triggered: self.vblank_triggered.replace(false),
+            frame_counter: self.frame_counter.get(),
+        }
+    }
+
+    fn swap_framebuffer_address(&mut self, address: u32) {
+        self.address = address;
+    }
+
+    fn set_framebuffer_address(&mut self, address: u32) {
+        self.address = address;
+    }
+}
+
+fn assert_rotates<const N: usize>() {
+    let mut framebuffers = Framebuffers::<N, _>::new(MockVideo::free_running());
+    let mut backs = Vec::new();
+
+    for _ in 0..4 * N {
+        assert_ne!(framebuffers.back_index(), framebuffers.front_index());
+        assert_eq!(
+            framebuffers.video().address,
+            Framebuffers::<N, MockVideo>::buffer_address(framebuffers.front_index())
+        );
+
+        backs.push(framebuffers.back_index());
+        framebuffers.present();
+    }
+
+    // Every buffer is drawn into once in each run of `N` frames
+    for frames in backs.windows(N) {
+        let mut buffers = frames.to_vec();
+        buffers.sort();
+
+        assert_eq!(buffers, (0..N).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn rotates_through_every_buffer() {
+    assert_rotates::<2>();
+    assert_rotates::<3>();
+    assert_rotates::<4>();
+}
+
+#[test]
+fn flips_once_per_vblank() {
+    let mut framebuffers = Framebuffers::<3, _>::new(MockVideo::default());
+
+    framebuffers.present();
+
+    assert!(!framebuffers.poll());
+
+    framebuffers.video().start_vblank();
+
+    assert!(framebuffers.poll());
+    assert_eq!(framebuffers.front_index(), 1);
+    assert_eq!(
+        framebuffers.video().address,
+        Framebuffers::<3, MockVideo>::buffer_address(1)
+    );
+
+    // Still in the same vblank, so the next frame waits for another
+    framebuffers.present();
+
+    assert!(!framebuffers.poll());
+
+    framebuffers.video().end_vblank();
+
+    assert!(!framebuffers.poll());
+    assert_eq!(framebuffers.front_index(), 1);
+
+    framebuffers.video().start_vblank();
+
+    assert!(framebuffers.poll());
+    assert!(!framebuffers.poll());
+    assert_eq!(framebuffers.front_index(), 2);
+}
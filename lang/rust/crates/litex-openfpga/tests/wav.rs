@@ -0,0 +1,103 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::fs;
+
+use common::*;
+use litex_openfpga::*;
+
+fn load(name: &str, bytes: &[u8]) -> Result<Vec<StereoSample>, WavError> {
+    let directory = slot_directory(name);
+    fs::write(directory.join("0.bin"), bytes).unwrap();
+
+    File::new(MockBridge::new(&directory)).load_wav(0)
+}
+
+#[test]
+fn stereo_16_bit_at_output_rate() {
+    let bytes = wav(&[
+        fmt(1, 2, 48_000, 16),
+        chunk(
+            b"data",
+            &samples_16(&[1, -1, 1000, -1000, i16::MAX, i16::MIN]),
+        ),
+    ]);
+
+    assert_eq!(
+        load("stereo", &bytes).unwrap(),
+        [
+            StereoSample::new(1, -1),
+            StereoSample::new(1000, -1000),
+            StereoSample::new(i16::MAX, i16::MIN),
+        ]
+    );
+}
+
+#[test]
+fn mono_8_bit_is_upsampled() {
+    let bytes = wav(&[fmt(1, 1, 24_000, 8), chunk(b"data", &[128, 192, 64])]);
+    let samples = load("mono", &bytes).unwrap();
+
+    assert_eq!(
+        samples.iter().map(|sample| sample.left).collect::<Vec<_>>(),
+        [0, 8192, 16384, 0, -16384, -16384]
+    );
+    assert!(samples.iter().all(|sample| sample.left == sample.right));
+}
+
+#[test]
+fn downsampled_length() {
+    let data = samples_16(&[0; 441 * 2]);
+    let bytes = wav(&[fmt(1, 2, 44_100, 16), chunk(b"data", &data)]);
+    let directory = slot_directory("length");
+    fs::write(directory.join("0.bin"), &bytes).unwrap();
+
+    let mut file = File::new(MockBridge::new(&directory));
+    let decoder = WavDecoder::new(file.slot_reader(0)).unwrap();
+
+    assert_eq!(decoder.format().frames(), 441);
+    assert_eq!(decoder.format().output_length(), 480);
+    assert_eq!(file.load_wav(0).unwrap().len(), 480);
+}
+
+#[test]
+fn skips_unknown_chunks() {
+    let bytes = wav(&[
+        chunk(b"LIST", b"odd"),
+        fmt(0xFFFE, 1, 48_000, 16),
+        chunk(b"fact", &[0; 4]),
+        chunk(b"data", &samples_16(&[42, -42])),
+    ]);
+
+    assert_eq!(
+        load("chunks", &bytes).unwrap(),
+        [StereoSample::mono(42), StereoSample::mono(-42)]
+    );
+}
+
+#[test]
+fn rejects_other_files() {
+    assert_eq!(
+        load("not-wav", b"RIFF\0\0\0\0AVI LIST"),
+        Err(WavError::NotWav)
+    );
+    assert_eq!(
+        load(
+            "24-bit",
+            &wav(&[fmt(1, 2, 48_000, 24), chunk(b"data", &[0; 6])])
+        ),
+        Err(WavError::UnsupportedFormat)
+    );
+    assert_eq!(
+        load(
+            "float",
+            &wav(&[fmt(3, 1, 48_000, 16), chunk(b"data", &[0; 2])])
+        ),
+        Err(WavError::UnsupportedFormat)
+    );
+    assert_eq!(
+        load("no-data", &wav(&[fmt(1, 1, 48_000, 16)])),
+        Err(WavError::Truncated)
+    );
+}
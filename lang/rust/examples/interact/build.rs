@@ -1,6 +1,8 @@
 use std::path::Path;
 
-/// Put the linker script somewhere the linker can find it.
+include!("src/settings.rs");
+
+/// Put the linker script somewhere the linker can find it, and generate `interact.json` from `src/settings.rs`.
 fn main() {
     let dest_path = Path::new("../../../linker/");
 
@@ -10,7 +12,12 @@ fn main() {
         dest_path.canonicalize().unwrap().display()
     );
 
+    INTERACT
+        .write_json_file("interact.json")
+        .expect("Invalid interact.json definition");
+
     println!("cargo:rerun-if-changed=regions.ld");
     println!("cargo:rerun-if-changed=memory.x");
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/settings.rs");
 }
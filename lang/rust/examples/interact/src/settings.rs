@@ -0,0 +1,138 @@
+// Shared with `build.rs`, which generates `interact.json` from `INTERACT`
+
+// Only the items used, as a glob would also bring the crate's `println!` into `build.rs`
+use litex_openfpga::{
+    InteractAddress, InteractDefinition, InteractKind, InteractVariable, ListOption,
+};
+
+pub const CHOICES: &[ListOption] = &[
+    ListOption {
+        value: 0,
+        name: "Foo",
+    },
+    ListOption {
+        value: 1,
+        name: "Bar",
+    },
+    ListOption {
+        value: 2,
+        name: "Foobar",
+    },
+    ListOption {
+        value: 3,
+        name: "Foofoo",
+    },
+];
+
+const fn radio(id: u32, name: &'static str, value: u32, default: bool) -> InteractVariable {
+    InteractVariable::new(
+        id,
+        name,
+        InteractAddress::Index(1),
+        InteractKind::Radio {
+            group: 100,
+            value,
+            default,
+        },
+    )
+    .persist()
+    .write_only()
+}
+
+// The variables read back through `Settings` in `main.rs`, which binds its fields to their indices
+
+pub const CHECKED: InteractVariable = InteractVariable::new(
+    200,
+    "Index 0",
+    InteractAddress::Index(0),
+    InteractKind::Check {
+        value: 123,
+        default: false,
+    },
+)
+.persist()
+.write_only();
+
+pub const RADIO: [InteractVariable; 4] = [
+    radio(201, "Index 1: A", 0xFF, true),
+    radio(202, "Index 1: B", 0xFF00, false),
+    radio(203, "Index 1: C", 0xFF_0000, false),
+    radio(204, "Index 1: D", 0xFF00_0000, false),
+];
+
+pub const CHOICE: InteractVariable = InteractVariable::new(
+    205,
+    "Index 2",
+    InteractAddress::Index(2),
+    InteractKind::List {
+        default: 0,
+        options: CHOICES,
+    },
+)
+.persist()
+.write_only();
+
+pub const SLIDER: InteractVariable = InteractVariable::new(
+    207,
+    "Index 4",
+    InteractAddress::Index(4),
+    InteractKind::SliderU32 {
+        default: 100,
+        min: 0,
+        max: 320,
+        adjust_small: 1,
+        adjust_large: 0x10,
+    },
+)
+.persist();
+
+pub const INTERACT: InteractDefinition = InteractDefinition::new(&[
+    InteractVariable::new(
+        0,
+        "Reset core",
+        InteractAddress::Core(0x1000_0000),
+        InteractKind::Action { value: 1 },
+    ),
+    InteractVariable::new(
+        100,
+        "Enable + Btn Reset",
+        InteractAddress::Core(0x1000_0004),
+        InteractKind::Check {
+            value: 1,
+            default: false,
+        },
+    )
+    .persist()
+    .write_only(),
+    InteractVariable::new(
+        101,
+        "Enable JTAG UART",
+        InteractAddress::Core(0x1000_0008),
+        InteractKind::Check {
+            value: 1,
+            default: false,
+        },
+    )
+    .persist()
+    .write_only(),
+    CHECKED,
+    RADIO[0],
+    RADIO[1],
+    RADIO[2],
+    RADIO[3],
+    CHOICE,
+    InteractVariable::new(
+        206,
+        "Index 3: Action",
+        InteractAddress::Index(3),
+        InteractKind::Action { value: 64 },
+    ),
+    SLIDER,
+    InteractVariable::new(
+        208,
+        "5:",
+        InteractAddress::Index(5),
+        InteractKind::NumberU32 { default: 0 },
+    )
+    .disabled(),
+]);
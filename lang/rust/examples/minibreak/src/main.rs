@@ -11,7 +11,6 @@ use core::slice::from_raw_parts_mut;
 extern crate alloc;
 
 use embedded_alloc::Heap;
-use litex_hal as hal;
 use litex_openfpga::*;
 use litex_pac as pac;
 use riscv_rt::entry;
@@ -20,11 +19,6 @@ mod irect2;
 
 // Basic platform support
 
-// Definition is required for uart_printer.rs to work
-hal::uart! {
-    UART: pac::UART,
-}
-
 // Fix for missing main functions
 #[no_mangle]
 fn fminf(a: f32, b: f32) -> f32 {
@@ -65,63 +59,26 @@ static HEAP: Heap = Heap::empty();
 const DISPLAY_WIDTH: usize = pac::constants::MAX_DISPLAY_WIDTH as usize;
 const DISPLAY_HEIGHT: usize = pac::constants::MAX_DISPLAY_HEIGHT as usize;
 
-const READ_LENGTH: usize = 0x10000;
-
-fn render_init(framebuffer_address: *mut u16) {
-    let framebuffer =
-        unsafe { from_raw_parts_mut(framebuffer_address, DISPLAY_WIDTH * DISPLAY_HEIGHT) };
-
-    const PIXEL_MAX: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
-    for idx in 0..PIXEL_MAX {
-        framebuffer[idx] = 0xFFFF;
-    }
-}
-
-fn pixel(framebuffer_address: *mut u16, x: usize, y: usize) -> &'static mut u16 {
-    let framebuffer = unsafe { from_raw_parts_mut(framebuffer_address as *mut u16, READ_LENGTH) };
-
-    &mut framebuffer[y * DISPLAY_WIDTH + x]
-}
-
-// Gamepad controls
-
-#[repr(u16)]
-#[allow(dead_code)]
-enum PocketControls {
-    DpadUp = 1 << 0,
-    DpadDown = 1 << 1,
-    DpadLeft = 1 << 2,
-    DpadRight = 1 << 3,
-    FaceA = 1 << 4,
-    FaceB = 1 << 5,
-    FaceX = 1 << 6,
-    FaceY = 1 << 7,
-    TrigL1 = 1 << 8,
-    TrigR1 = 1 << 9,
-    TrigL2 = 1 << 10,
-    TrigR2 = 1 << 11,
-    TrigL3 = 1 << 12,
-    TrigR3 = 1 << 13,
-    FaceSelect = 1 << 14,
-    FaceStart = 1 << 15,
-}
-
 // This is the entry point for the application.
 // It is not allowed to return.
 #[entry]
 fn main() -> ! {
-    let peripherals = unsafe { pac::Peripherals::steal() };
-
     // Initialize the allocator BEFORE you use it
     unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) };
 
+    let mut pocket = Pocket::take().unwrap();
+    set_printer(pocket.uart);
+
     println!("-- Minibreak --");
 
     // Framebuffer pointer
     // Note we also had the option of simply picking an address and writing dma_base instead of reading it
-    let fb: *mut u16 = peripherals.VIDEO_FRAMEBUFFER.dma_base.read().bits() as *mut u16;
+    let fb = pocket.video.framebuffer_address() as *mut Rgb565;
+    let fb = unsafe { from_raw_parts_mut(fb, DISPLAY_WIDTH * DISPLAY_HEIGHT) };
+    let mut canvas = Canvas::new(fb, DISPLAY_WIDTH, DISPLAY_HEIGHT);
 
-    render_init(fb);
+    canvas.clear(Rgb565::WHITE);
+    canvas.set_mode(DrawMode::Xor);
 
     // "APP"
     {
@@ -139,7 +96,13 @@ fn main() -> ! {
         let mut paused = false;
         let mut dead = false;
         let mut won = false;
-        let mut cont1_key_last = 0; // State of controller on previous loop
+        let mut input = InputState::new(); // Tracks controller state between frames
+        let mut clock = FrameClock::new(); // Paces the game loop to the display
+
+        // Docked analog controllers can steer with the left stick
+        input
+            .analog_mut(0)
+            .set_dpad_emulation(Some(DEFAULT_DPAD_THRESHOLD));
 
         // Display
 
@@ -248,7 +211,7 @@ fn main() -> ! {
         let ball_facing_start = {
             // Randomly start off moving left or right; use the current UTC as a very weak RNG
             let mut ball_facing = BALL_FACING_START;
-            if 0 == peripherals.APF_RTC.unix_seconds.read().bits() % 2 {
+            if 0 == pocket.rtc.unix_seconds() % 2 {
                 ball_facing.x *= -1
             }
             ball_facing
@@ -271,54 +234,43 @@ fn main() -> ! {
 
         // Gfx support
 
-        fn fill(fb: *mut u16, rect: IRect2, color: u16) {
+        fn fill(canvas: &mut Canvas, rect: IRect2, color: u16) {
             // XOR rectangle with given color
-            for y in rect.ul.y..rect.br.y {
-                for x in rect.ul.x..rect.br.x {
-                    *pixel(fb, x as usize, y as usize) ^= color;
-                }
-            }
+            let size = rect.size();
+
+            canvas.fill_rect(
+                Rect::new(rect.ul.x, rect.ul.y, size.x as u32, size.y as u32),
+                Rgb565(color),
+            );
         }
 
         // Initial draw
         for player in &players {
-            fill(fb, player.rect, PLAYER_COLOR);
+            fill(&mut canvas, player.rect, PLAYER_COLOR);
         }
         for ball in &balls {
-            fill(fb, ball.rect, BALL_COLOR);
+            fill(&mut canvas, ball.rect, BALL_COLOR);
         }
         for vader in &vaders {
-            fill(fb, vader.rect, VADER_COLOR);
+            fill(&mut canvas, vader.rect, VADER_COLOR);
         }
 
         loop {
-            // Busy loop until VBLANK begins, signaling next frame ready to go.
+            // Wait until VBLANK begins, signaling next frame ready to go.
             // We'd like to do all drawing inside VBLANK to prevent tearing.
-            loop {
-                let video = peripherals.APF_VIDEO.video.read();
-
-                if video.vblank_triggered().bit() {
-                    break;
-                }
-            }
+            clock.wait_for_vblank(&pocket.video);
 
             // Controls
 
-            let cont1_key = peripherals.APF_INPUT.cont1_key.read().bits() as u16; // Bitmask (crop out analog sticks)
-            let cont1_key_edge = (!cont1_key_last) & cont1_key; // Bitmask is 1 iff a button press is *new this frame*
-            cont1_key_last = cont1_key;
-
-            use PocketControls::*;
+            input.update(&pocket.input, clock.frame_counter());
 
             // Controls: Reset
-            if cont1_key_edge & FaceStart as u16 != 0 {
-                unsafe {
-                    peripherals.CTRL.reset.write(|w| w.bits(1));
-                } // 1 resets entire SOC
+            if input.just_pressed(0, Buttons::FACE_START) {
+                pocket.ctrl.reset_soc();
             }
 
             // Controls: Pause
-            if !dead && cont1_key_edge & FaceSelect as u16 != 0 {
+            if !dead && input.just_pressed(0, Buttons::FACE_SELECT) {
                 paused = !paused;
             }
 
@@ -330,7 +282,7 @@ fn main() -> ! {
                 // (When one block is left, have it start moving so you aren't stuck unable to hit it.)
                 if vaders.len() == 1 {
                     let vader = &mut vaders[0];
-                    fill(fb, vader.rect, VADER_COLOR); // Erase
+                    fill(&mut canvas, vader.rect, VADER_COLOR); // Erase
 
                     if final_vader_facing == 0 {
                         // This is our first frame with only 1 vader
@@ -354,7 +306,7 @@ fn main() -> ! {
                         vader.rect.offset(-vader_move)
                     };
 
-                    fill(fb, vader.rect, VADER_COLOR); // Draw
+                    fill(&mut canvas, vader.rect, VADER_COLOR); // Draw
                 }
 
                 // Player mechanics
@@ -363,21 +315,21 @@ fn main() -> ! {
                     // Controls: Movement
                     // Here we go to quite some trouble to handle the case of left and right held down at once--
                     // Which is impossible on the Analogue builtin buttons. But maybe it could happen on bluetooth
-                    const LR_MASK: u16 = DpadLeft as u16 | DpadRight as u16;
-                    player.facing = if cont1_key & LR_MASK == LR_MASK {
+                    const LR_MASK: Buttons = Buttons::DPAD_LEFT.union(Buttons::DPAD_RIGHT);
+                    player.facing = if input.held(0).contains(LR_MASK) {
                         // L+R both down case:
-                        if cont1_key_edge & DpadLeft as u16 != 0 {
+                        if input.just_pressed(0, Buttons::DPAD_LEFT) {
                             -1
-                        } else if cont1_key_edge & DpadRight as u16 != 0 {
+                        } else if input.just_pressed(0, Buttons::DPAD_RIGHT) {
                             1
                         } else {
                             player.facing
                         }
                     } else {
                         // Only one of L+R down case:
-                        if cont1_key & DpadLeft as u16 != 0 {
+                        if input.is_held(0, Buttons::DPAD_LEFT) {
                             -1
-                        } else if cont1_key & DpadRight as u16 != 0 {
+                        } else if input.is_held(0, Buttons::DPAD_RIGHT) {
                             1
                         } else {
                             0
@@ -387,7 +339,7 @@ fn main() -> ! {
                     if player.facing != 0 {
                         // If moving
                         if CONFIG_CHAOS < 2 {
-                            fill(fb, player.rect, PLAYER_COLOR); // Erase
+                            fill(&mut canvas, player.rect, PLAYER_COLOR); // Erase
                         }
 
                         // Update based on facing, then force back inside screen.
@@ -396,14 +348,14 @@ fn main() -> ! {
                             .offset(IVec2::new(player.facing * PLAYER_SPEED, 0))
                             .force_enclose_x(screen);
 
-                        fill(fb, player.rect, PLAYER_COLOR); // Draw
+                        fill(&mut canvas, player.rect, PLAYER_COLOR); // Draw
                     }
                 }
 
                 // Ball
                 for ball in &mut balls {
                     if CONFIG_CHAOS < 1 {
-                        fill(fb, ball.rect, BALL_COLOR); // Erase
+                        fill(&mut canvas, ball.rect, BALL_COLOR); // Erase
                     }
 
                     // Step one pixel at a time, one axis at a time.
@@ -461,7 +413,7 @@ fn main() -> ! {
                                     reflect = true;
                                     destroy = Some(idx);
                                     audio_bleep_high = true; // Only vaders bleep high
-                                    fill(fb, vader.rect, VADER_COLOR); // Erase vader (screen)
+                                    fill(&mut canvas, vader.rect, VADER_COLOR); // Erase vader (screen)
                                 }
                             }
 
@@ -489,16 +441,17 @@ fn main() -> ! {
                         }
                     }
 
-                    fill(fb, ball.rect, BALL_COLOR); // Draw
+                    fill(&mut canvas, ball.rect, BALL_COLOR); // Draw
                 }
             }
 
             // Audio generation
 
             // Generate enough samples to fill us up to our desired buffer (a frame plus a safety margin)
-            let audio_needed =
-                AUDIO_TARGET - peripherals.APF_AUDIO.buffer_fill.read().bits() as i32;
-            for _ in 0..audio_needed {
+            let audio_needed = (AUDIO_TARGET - pocket.audio.poll_fill() as i32).max(0) as usize;
+            let mut samples = [StereoSample::SILENCE; AUDIO_TARGET as usize];
+
+            for sample in samples[..audio_needed].iter_mut() {
                 let mut lfo_engaged = false; // True if the background "low pitched hum" is playing
 
                 if !paused {
@@ -559,14 +512,13 @@ fn main() -> ! {
                     value /= AUDIO_LFO_MAX as u32;
                 }
 
-                // Output value is two stereo i16s packed into one u32
                 // Notice we did our math above in u32; it doesn't matter because bit 15 is always 0
-                value = value | (value << 16);
-
-                unsafe { peripherals.APF_AUDIO.out.write(|w| w.bits(value)) };
+                *sample = StereoSample::mono(value as i16);
             }
 
-            unsafe { peripherals.APF_AUDIO.playback_en.write(|w| w.bits(1)) };
+            pocket.audio.push_samples(&samples[..audio_needed]);
+
+            pocket.audio.set_playback(true);
         }
     }
 
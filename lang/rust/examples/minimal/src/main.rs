@@ -25,6 +25,9 @@ fn panic(info: &PanicInfo) -> ! {
 fn main() -> ! {
     unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) };
 
+    let pocket = Pocket::take().unwrap();
+    set_printer(pocket.uart);
+
     println!("Rust: Hello, world!");
 
     loop {}
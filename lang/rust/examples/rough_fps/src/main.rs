@@ -6,11 +6,8 @@ extern crate alloc;
 use alloc::format;
 use core::cell::RefCell;
 use core::panic::PanicInfo;
-use core::slice::from_raw_parts_mut;
 use core::time::Duration;
 use pac::constants;
-use slint::platform::software_renderer::MinimalSoftwareWindow;
-use slint::platform::software_renderer::{RepaintBufferType, Rgb565Pixel};
 use slint::Timer;
 
 use alloc::{boxed::Box, rc::Rc};
@@ -41,34 +38,28 @@ fn panic(info: &PanicInfo) -> ! {
 #[global_allocator]
 static HEAP: Heap = Heap::empty();
 
-const TEST_BUFFER_INTERNAL_ADDRESS: u32 = constants::VIDEO_FRAMEBUFFER_BASE;
-
-const TEST_PIXEL_BUFFER_ADDRESS: *mut Rgb565Pixel =
-    constants::VIDEO_FRAMEBUFFER_BASE as *mut Rgb565Pixel;
-
 const TEST_WORD_ADDRESS: *mut u32 = 0x4030_0000 as *mut u32;
 
 // This is the entry point for the application.
 // It is not allowed to return.
 #[entry]
 fn main() -> ! {
-    let peripherals = unsafe { pac::Peripherals::steal() };
-
     // Initialize the allocator BEFORE you use it
     unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
 
+    let pocket = Pocket::take().unwrap();
+    set_printer(pocket.uart);
+
     println!("Heap created");
 
-    let buffer = unsafe {
-        from_raw_parts_mut(
-            TEST_PIXEL_BUFFER_ADDRESS,
-            (constants::MAX_DISPLAY_WIDTH * constants::MAX_DISPLAY_HEIGHT) as usize,
-        )
-    };
+    // Draw into one buffer while the other is displayed, flipping at vblank. Only what changed is redrawn
+    let framebuffers = Rc::new(RefCell::new(SlintFramebuffers::new(
+        Framebuffers::<2>::new(pocket.video),
+    )));
 
     // Initialize a window (we'll need it later).
-    let window = MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer);
-    slint::platform::set_platform(Box::new(SlintPlatform::new(window.clone(), 57_120_000)))
+    let window = framebuffers.borrow().create_window();
+    slint::platform::set_platform(Box::new(SlintPlatform::new(window.clone(), pocket.timer)))
         .unwrap();
 
     println!("Creating UI");
@@ -85,40 +76,6 @@ fn main() -> ! {
         constants::MAX_DISPLAY_HEIGHT as u32,
     ));
 
-    // FB Off
-    unsafe {
-        peripherals
-            .VIDEO_FRAMEBUFFER_VTG
-            .enable
-            .write(|w| w.bits(0));
-        peripherals
-            .VIDEO_FRAMEBUFFER
-            .dma_enable
-            .write(|w| w.bits(0));
-    }
-
-    println!("FB Off");
-
-    // Set read page
-    unsafe {
-        peripherals
-            .VIDEO_FRAMEBUFFER
-            .dma_base
-            .write(|w| w.bits(TEST_BUFFER_INTERNAL_ADDRESS));
-    }
-
-    // FB On
-    unsafe {
-        peripherals
-            .VIDEO_FRAMEBUFFER_VTG
-            .enable
-            .write(|w| w.bits(1));
-        peripherals
-            .VIDEO_FRAMEBUFFER
-            .dma_enable
-            .write(|w| w.bits(1));
-    }
-
     let timer = Timer::default();
 
     let shared_ui = Rc::new(RefCell::new(ui));
@@ -126,6 +83,7 @@ fn main() -> ! {
 
     let timer_shared_ui = shared_ui.clone();
     let draws_since_last_tick_timer = draws_since_last_tick.clone();
+    let timer_framebuffers = framebuffers.clone();
 
     timer.start(
         slint::TimerMode::Repeated,
@@ -139,6 +97,11 @@ fn main() -> ! {
 
             println!("FPS: {value}");
 
+            let mut framebuffers = timer_framebuffers.borrow_mut();
+
+            println!("Redraws: {}", framebuffers.stats());
+            framebuffers.reset_stats();
+
             // let current_value = unsafe { MAIN_RAM_BASE.read_volatile() };
             let current_value = unsafe { TEST_WORD_ADDRESS.read_volatile() };
 
@@ -149,13 +112,12 @@ fn main() -> ! {
     );
 
     let mut last_address = 0;
-    let mut button_pressed = false;
 
     loop {
         slint::platform::update_timers_and_animations();
 
         window.draw_if_needed(|renderer| {
-            renderer.render(buffer, constants::MAX_DISPLAY_WIDTH as usize);
+            framebuffers.borrow_mut().render(renderer);
 
             let ui = shared_ui.borrow();
 
@@ -164,73 +126,25 @@ fn main() -> ! {
             let mut x = ui_positioner.get_x();
             let mut y = ui_positioner.get_y();
 
-            let cont1_key = peripherals.APF_INPUT.cont1_key.read().bits();
+            let cont1_key = pocket.input.buttons(0);
 
-            if cont1_key & 0x1 != 0 {
+            if cont1_key.contains(Buttons::DPAD_UP) {
                 // Up
                 y -= 1.0;
-            } else if cont1_key & 0x2 != 0 {
+            } else if cont1_key.contains(Buttons::DPAD_DOWN) {
                 // Down
                 y += 1.0;
             }
 
-            if cont1_key & 0x4 != 0 {
+            if cont1_key.contains(Buttons::DPAD_LEFT) {
                 // Left
                 x -= 1.0;
-            } else if cont1_key & 0x8 != 0 {
+            } else if cont1_key.contains(Buttons::DPAD_RIGHT) {
                 // Right
                 x += 1.0;
             }
 
-            if cont1_key & 0x100 != 0 {
-                if !button_pressed {
-                    button_pressed = true;
-
-                    unsafe {
-                        peripherals
-                            .VIDEO_FRAMEBUFFER_VTG
-                            .enable
-                            .write(|w| w.bits(0));
-                        peripherals
-                            .VIDEO_FRAMEBUFFER
-                            .dma_enable
-                            .write(|w| w.bits(0));
-
-                        peripherals
-                            .VIDEO_FRAMEBUFFER
-                            .dma_offset
-                            .write(|w| w.bits(0));
-                    }
-
-                    println!("FB Off");
-
-                    // Set read page
-                    unsafe {
-                        peripherals
-                            .VIDEO_FRAMEBUFFER
-                            .dma_base
-                            .write(|w| w.bits(TEST_BUFFER_INTERNAL_ADDRESS));
-                    }
-
-                    // FB On
-                    unsafe {
-                        peripherals
-                            .VIDEO_FRAMEBUFFER_VTG
-                            .enable
-                            .write(|w| w.bits(1));
-                        peripherals
-                            .VIDEO_FRAMEBUFFER
-                            .dma_enable
-                            .write(|w| w.bits(1));
-                    }
-
-                    println!("FB On");
-                }
-            } else {
-                button_pressed = false;
-            }
-
-            let current_address = peripherals.APF_BRIDGE.current_address.read().bits();
+            let current_address = pocket.bridge.current_address();
 
             if current_address != last_address {
                 last_address = current_address;
@@ -238,9 +152,7 @@ fn main() -> ! {
                 println!("Address: {current_address:x}")
             }
 
-            let status = peripherals.APF_BRIDGE.status.read().bits();
-
-            if status > 0 {
+            if pocket.bridge.check_op_complete() {
                 println!("Finished write");
             }
 
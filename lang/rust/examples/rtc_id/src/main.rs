@@ -1,12 +1,10 @@
 #![no_std]
 #![no_main]
 
-use ::slint::platform::software_renderer::{MinimalSoftwareWindow, RepaintBufferType};
 use alloc::format;
 use core::panic::PanicInfo;
-use core::slice::from_raw_parts_mut;
+use core::time::Duration;
 use pac::constants;
-use slint::platform::software_renderer::Rgb565Pixel;
 
 extern crate alloc;
 
@@ -34,31 +32,26 @@ fn panic(info: &PanicInfo) -> ! {
 #[global_allocator]
 static HEAP: Heap = Heap::empty();
 
-const FRAMEBUFFER_ADDRESS: *mut Rgb565Pixel = constants::VIDEO_FRAMEBUFFER_BASE as *mut Rgb565Pixel;
-
 // This is the entry point for the application.
 // It is not allowed to return.
 #[entry]
 fn main() -> ! {
-    let peripherals = unsafe { pac::Peripherals::steal() };
-
     // Initialize the allocator BEFORE you use it
     unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) };
 
+    let pocket = Pocket::take().unwrap();
+    set_printer(pocket.uart);
+
     println!("Rendering");
 
-    let buffer = unsafe {
-        from_raw_parts_mut(
-            FRAMEBUFFER_ADDRESS,
-            (constants::MAX_DISPLAY_WIDTH * constants::MAX_DISPLAY_HEIGHT) as usize,
-        )
-    };
-
-    let window = MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer);
-    slint::platform::set_platform(Box::new(SlintPlatform::new(
-        window.clone(),
-        constants::CONFIG_CLOCK_FREQUENCY,
-    )))
+    // Only the text that changes is redrawn, flipping between the buffers at vblank
+    let framebuffers = SlintFramebuffers::new(Framebuffers::<2>::new(pocket.video));
+
+    let window = framebuffers.create_window();
+    slint::platform::set_platform(Box::new(
+        SlintPlatform::new(window.clone(), pocket.timer)
+            .with_event_loop(framebuffers, pocket.input),
+    ))
     .unwrap();
 
     println!("Creating UI");
@@ -73,21 +66,30 @@ fn main() -> ! {
         constants::MAX_DISPLAY_HEIGHT,
     ));
 
-    loop {
-        slint::platform::update_timers_and_animations();
+    let device = pocket.device;
+    let rtc = pocket.rtc;
+    let ui_handle = ui.as_weak();
+
+    let timer = slint::Timer::default();
+
+    timer.start(
+        slint::TimerMode::Repeated,
+        Duration::from_millis(100),
+        move || {
+            let Some(ui) = ui_handle.upgrade() else {
+                return;
+            };
 
-        window.draw_if_needed(|renderer| {
-            renderer.render(buffer, constants::MAX_DISPLAY_WIDTH as usize);
-        });
+            let id = device.chip_id();
+            let time = rtc.date_time();
 
-        let id_low = peripherals.APF_ID.id0.read().bits();
-        let id_high = peripherals.APF_ID.id1.read().bits();
+            let data = ui.global::<Data>();
+            data.set_id(format!("{id}").into());
+            data.set_rtc(format!("{time}").into());
+        },
+    );
 
-        let id = (id_high as u64) << 32 | (id_low as u64);
-        let time = peripherals.APF_RTC.unix_seconds.read().bits();
+    slint::run_event_loop().unwrap();
 
-        let data = ui.global::<Data>();
-        data.set_id(format!("{id:x}").into());
-        data.set_rtc(format!("{time}").into());
-    }
+    panic!("Event loop exited");
 }
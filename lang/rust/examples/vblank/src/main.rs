@@ -8,7 +8,6 @@ extern crate alloc;
 
 use embedded_alloc::Heap;
 use litex_openfpga::*;
-use litex_pac as pac;
 use riscv_rt::entry;
 
 const HEAP_SIZE: usize = 200 * 1024;
@@ -29,25 +28,24 @@ static HEAP: Heap = Heap::empty();
 // It is not allowed to return.
 #[entry]
 fn main() -> ! {
-    let peripherals = unsafe { pac::Peripherals::steal() };
-
     // Initialize the allocator BEFORE you use it
     unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) };
 
+    let pocket = Pocket::take().unwrap();
+    set_printer(pocket.uart);
+
     let mut counter = 0;
     let mut in_vblank = false;
 
     loop {
-        let video_data = peripherals.APF_VIDEO.video.read();
-        let vblank_triggered = video_data.vblank_triggered().bit();
-
-        let frame_count = video_data.frame_counter().bits();
+        let video_status = pocket.video.status();
+        let frame_count = video_status.frame_counter;
 
-        if vblank_triggered {
+        if video_status.vblank_triggered {
             println!("Vblank started at {counter} {frame_count}");
         }
 
-        let vblank = video_data.vblank_status().bit();
+        let vblank = video_status.vblank;
 
         if vblank && !in_vblank {
             in_vblank = true;